@@ -0,0 +1,429 @@
+//! Monte Carlo tree search, for the hardest AI.
+//!
+//! The tree alternates between the moves players choose and the dice deciding attacks. An
+//! attack leads to a chance node with two outcomes, captured or repelled, weighted by the exact
+//! odds. Reinforcements are too varied to branch on, so ending a turn rolls them fresh on every
+//! pass through the tree, and the moves under it are only offered while they are still legal.
+//!
+//! All randomness comes from a seeded generator, so with only an iteration budget the same
+//! position and seed always give the same move. A time budget trades that for a bounded wait.
+
+use rand::{Rng, SeedableRng};
+
+use super::Strategy;
+use crate::board::{Board, PlayerId};
+use crate::game::{Action, Game};
+use crate::probability::win_probability;
+use crate::rules::{self, Attack, AttackResult, MAX_DICE};
+use crate::seed::GameRng;
+
+use std::time::{Duration, Instant};
+
+/// How much exploring unvisited moves is favored over exploiting good ones.
+const EXPLORATION: f64 = 0.5;
+
+/// When to stop searching and pick a move.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Budget {
+    pub iterations: u32,
+    /// stop early once this much time has passed, at the cost of determinism
+    pub time: Option<Duration>,
+}
+
+impl Default for Budget {
+    fn default() -> Budget {
+        Budget {
+            iterations: 1000,
+            time: None,
+        }
+    }
+}
+
+/// Everything the search needs to know about a game in progress.
+#[derive(Clone, Debug)]
+pub struct Position {
+    pub board: Board,
+    /// every player, in turn order
+    pub order: Vec<PlayerId>,
+    /// index into `order` of whoever's turn it is
+    pub current: usize,
+    /// reserve dice, indexed by player id
+    pub reserves: Vec<u32>,
+    /// turns ended since the search started
+    ended: usize,
+}
+
+impl Position {
+    pub fn from_game(game: &Game) -> Position {
+        let order = game.players().to_vec();
+        let current = order
+            .iter()
+            .position(|&player| player == game.current_player())
+            .unwrap_or(0);
+        let seats = order.iter().map(|player| player.0 + 1).max().unwrap_or(0);
+        let reserves = (0..seats).map(|id| game.reserve(PlayerId(id))).collect();
+        Position {
+            board: game.board().clone(),
+            order,
+            current,
+            reserves,
+            ended: 0,
+        }
+    }
+
+    /// A position with only the board to go on: everyone still on it plays in id order, and
+    /// nobody has any dice in reserve.
+    pub fn from_board(board: &Board, player: PlayerId) -> Position {
+        let mut order = board.players();
+        if !order.contains(&player) {
+            order.push(player);
+        }
+        order.sort();
+        let current = order.iter().position(|&p| p == player).unwrap_or(0);
+        let seats = order.iter().map(|player| player.0 + 1).max().unwrap_or(0);
+        Position {
+            board: board.clone(),
+            order,
+            current,
+            reserves: vec![0; seats],
+            ended: 0,
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.order[self.current]
+    }
+
+    /// Every attack the current player can make, then ending the turn.
+    fn actions(&self) -> Vec<Action> {
+        let mut actions: Vec<Action> = rules::legal_attacks(&self.board, self.player())
+            .into_iter()
+            .map(Action::Attack)
+            .collect();
+        actions.push(Action::EndTurn);
+        actions
+    }
+
+    fn attack(&mut self, attack: Attack, result: AttackResult) {
+        let player = self.player();
+        rules::resolve(&mut self.board, player, attack, result);
+    }
+
+    /// Reinforce the current player and move on to the next one still on the board.
+    fn end_turn<R: Rng>(&mut self, rng: &mut R) {
+        let player = self.player();
+        let reserve = self.reserves[player.0];
+        self.reserves[player.0] = rules::reinforce(&mut self.board, player, reserve, rng).reserve;
+        self.ended += 1;
+        for _ in 0..self.order.len() {
+            self.current = (self.current + 1) % self.order.len();
+            if self.board.territories_of(self.player()).next().is_some() {
+                break;
+            }
+        }
+    }
+
+    /// The player who owns every territory, if there is one.
+    fn winner(&self) -> Option<PlayerId> {
+        let players = self.board.players();
+        if players.len() == 1 {
+            Some(players[0])
+        } else {
+            None
+        }
+    }
+
+    /// How well each player is doing, indexed by player id: 1.0 for a win, otherwise their
+    /// share of the board with their largest group counting extra.
+    fn score(&self) -> Vec<f64> {
+        let total = self.board.territory_count().max(1) as f64;
+        let mut scores = vec![0.0; self.reserves.len()];
+        if let Some(winner) = self.winner() {
+            scores[winner.0] = 1.0;
+            return scores;
+        }
+        for &player in &self.order {
+            let owned = self.board.territories_of(player).count() as f64;
+            let group = rules::largest_group(&self.board, player) as f64;
+            scores[player.0] = (owned + group) / (2.0 * total);
+        }
+        scores
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    /// a player picks a move; each child follows one action
+    Decision {
+        children: Vec<(Action, usize)>,
+        stats: Stats,
+    },
+    /// the dice decide an attack; children are the captured and repelled outcomes
+    Chance {
+        attack: Attack,
+        outcomes: [Option<usize>; 2],
+        stats: Stats,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Stats {
+    visits: u32,
+    /// total score for each player, indexed by player id
+    totals: Vec<f64>,
+}
+
+impl Node {
+    fn stats(&self) -> &Stats {
+        match self {
+            Node::Decision { stats, .. } | Node::Chance { stats, .. } => stats,
+        }
+    }
+
+    fn stats_mut(&mut self) -> &mut Stats {
+        match self {
+            Node::Decision { stats, .. } | Node::Chance { stats, .. } => stats,
+        }
+    }
+}
+
+/// Picks moves by searching ahead with Monte Carlo tree search.
+#[derive(Clone, Debug)]
+pub struct MctsAi {
+    pub budget: Budget,
+    rng: GameRng,
+}
+
+impl MctsAi {
+    pub fn new(budget: Budget, seed: u64) -> MctsAi {
+        MctsAi {
+            budget,
+            rng: GameRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Search from `position` and return the move that was explored the most.
+    pub fn search(&mut self, position: &Position) -> Action {
+        let actions = position.actions();
+        if actions.len() == 1 {
+            return Action::EndTurn;
+        }
+
+        let seats = position.reserves.len();
+        let mut nodes = vec![decision(seats)];
+        let started = Instant::now();
+        for _ in 0..self.budget.iterations {
+            if let Some(limit) = self.budget.time {
+                if started.elapsed() >= limit {
+                    break;
+                }
+            }
+            self.iterate(&mut nodes, position, seats);
+        }
+
+        match &nodes[0] {
+            Node::Decision { children, .. } => children
+                .iter()
+                .max_by_key(|&&(_, child)| nodes[child].stats().visits)
+                .map(|&(action, _)| action)
+                .unwrap_or(Action::EndTurn),
+            Node::Chance { .. } => unreachable!("the root is always a decision"),
+        }
+    }
+
+    /// One pass: walk down the tree, add a node, play out the rest, and record the score.
+    fn iterate(&mut self, nodes: &mut Vec<Node>, root: &Position, seats: usize) {
+        let mut position = root.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+        loop {
+            if position.winner().is_some() {
+                break;
+            }
+            let (next, added) = match nodes[node].clone() {
+                Node::Decision { children, stats } => {
+                    let legal = position.actions();
+                    let untried = legal
+                        .iter()
+                        .find(|action| children.iter().all(|(tried, _)| tried != *action));
+                    let (action, child, added) = match untried {
+                        Some(&action) => {
+                            let child = nodes.len();
+                            nodes.push(match action {
+                                Action::Attack(attack) => chance(attack, seats),
+                                Action::EndTurn => decision(seats),
+                            });
+                            if let Node::Decision { children, .. } = &mut nodes[node] {
+                                children.push((action, child));
+                            }
+                            (action, child, true)
+                        }
+                        None => {
+                            let player = position.player();
+                            let (action, child) = children
+                                .iter()
+                                .filter(|(action, _)| legal.contains(action))
+                                .max_by(|a, b| {
+                                    let a = uct(nodes[a.1].stats(), stats.visits, player);
+                                    let b = uct(nodes[b.1].stats(), stats.visits, player);
+                                    a.partial_cmp(&b).expect("scores are finite")
+                                })
+                                .cloned()
+                                .expect("ending the turn is always legal");
+                            (action, child, false)
+                        }
+                    };
+                    if action == Action::EndTurn {
+                        position.end_turn(&mut self.rng);
+                    }
+                    // a new attack still needs its dice rolled before it is a leaf
+                    let leaf = added && action == Action::EndTurn;
+                    (child, leaf)
+                }
+                Node::Chance {
+                    attack, outcomes, ..
+                } => {
+                    let odds = win_probability(
+                        position.board.dice(attack.from),
+                        position.board.dice(attack.to),
+                    );
+                    let (result, index) = if self.rng.gen_bool(odds.max(0.0).min(1.0)) {
+                        (AttackResult::Captured, 0)
+                    } else {
+                        (AttackResult::Repelled, 1)
+                    };
+                    position.attack(attack, result);
+                    match outcomes[index] {
+                        Some(child) => (child, false),
+                        None => {
+                            let child = nodes.len();
+                            nodes.push(decision(seats));
+                            if let Node::Chance { outcomes, .. } = &mut nodes[node] {
+                                outcomes[index] = Some(child);
+                            }
+                            (child, true)
+                        }
+                    }
+                }
+            };
+            path.push(next);
+            node = next;
+            if added {
+                break;
+            }
+        }
+
+        let scores = self.playout(position);
+        for &node in &path {
+            let stats = nodes[node].stats_mut();
+            stats.visits += 1;
+            for (total, score) in stats.totals.iter_mut().zip(scores.iter()) {
+                *total += score;
+            }
+        }
+    }
+
+    /// Play on with a quick greedy policy until everyone has had a turn since the search started,
+    /// then score the position. Stopping at the same point whatever the tree did keeps ending the
+    /// turn early from looking better than it is.
+    fn playout(&mut self, mut position: Position) -> Vec<f64> {
+        let horizon = position.order.len();
+        while position.ended < horizon && position.winner().is_none() {
+            let player = position.player();
+            let best = rules::legal_attacks(&position.board, player)
+                .into_iter()
+                .map(|attack| {
+                    let attacking = position.board.dice(attack.from);
+                    let odds = win_probability(attacking, position.board.dice(attack.to));
+                    (attack, odds, attacking)
+                })
+                .filter(|&(_, odds, attacking)| odds >= 0.5 || attacking == MAX_DICE)
+                .max_by(|a, b| a.1.partial_cmp(&b.1).expect("odds are finite"));
+            match best {
+                Some((attack, odds, _)) => {
+                    let result = if self.rng.gen_bool(odds) {
+                        AttackResult::Captured
+                    } else {
+                        AttackResult::Repelled
+                    };
+                    position.attack(attack, result);
+                }
+                None => position.end_turn(&mut self.rng),
+            }
+        }
+        position.score()
+    }
+}
+
+impl Strategy for MctsAi {
+    fn next_action(&mut self, board: &Board, player: PlayerId) -> Action {
+        self.search(&Position::from_board(board, player))
+    }
+}
+
+fn decision(seats: usize) -> Node {
+    Node::Decision {
+        children: vec![],
+        stats: Stats::new(seats),
+    }
+}
+
+fn chance(attack: Attack, seats: usize) -> Node {
+    Node::Chance {
+        attack,
+        outcomes: [None, None],
+        stats: Stats::new(seats),
+    }
+}
+
+impl Stats {
+    fn new(seats: usize) -> Stats {
+        Stats {
+            visits: 0,
+            totals: vec![0.0; seats],
+        }
+    }
+}
+
+/// The upper confidence bound of a child, from the point of view of `player`.
+fn uct(child: &Stats, parent_visits: u32, player: PlayerId) -> f64 {
+    if child.visits == 0 {
+        return std::f64::INFINITY;
+    }
+    let visits = child.visits as f64;
+    let mean = child.totals[player.0] / visits;
+    mean + EXPLORATION * ((parent_visits.max(1) as f64).ln() / visits).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameSettings;
+    use crate::mapgen;
+    use crate::seed::MapSeed;
+
+    #[test]
+    fn the_same_seed_picks_the_same_move() {
+        let settings = GameSettings {
+            width: 16,
+            height: 16,
+            area_size: 6,
+            player_count: Some(3),
+            ..GameSettings::default()
+        };
+        let budget = Budget {
+            iterations: 300,
+            time: None,
+        };
+        for seed in 0..5 {
+            let map = MapSeed(seed);
+            let board = mapgen::generate(&settings, &mut map.rng()).board;
+            let players = (0..3).map(PlayerId).collect();
+            let position = Position::from_game(&Game::new(board, players, map.game_rng()));
+
+            let first = MctsAi::new(budget, seed).search(&position);
+            let second = MctsAi::new(budget, seed).search(&position);
+            assert_eq!(first, second, "seed {}", seed);
+        }
+    }
+}
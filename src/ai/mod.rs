@@ -0,0 +1,88 @@
+//! Computer players.
+//!
+//! A strategy looks at the board and picks the next action for a player: some attacks, one at a
+//! time, and finally ending the turn. It only ever reads the board; the gameplay state applies
+//! the actions through the rules like any other player's.
+
+mod mcts;
+mod system;
+
+pub use mcts::{Budget, MctsAi, Position};
+pub use system::{AiControl, AiSystem};
+
+use crate::board::{Board, PlayerId};
+use crate::game::Action;
+use crate::probability::win_probability;
+use crate::rules::{self, Attack, MAX_DICE};
+use crate::setup::Difficulty;
+
+/// Something that can play a turn.
+pub trait Strategy {
+    /// The next thing `player` should do on `board`. Called again after each attack resolves, until
+    /// it returns `Action::EndTurn`.
+    fn next_action(&mut self, board: &Board, player: PlayerId) -> Action;
+}
+
+/// Attacks whenever the odds are good enough, preferring attacks that grow its largest group.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeuristicAi {
+    /// the lowest chance of winning it will attack with
+    pub min_odds: f64,
+}
+
+impl HeuristicAi {
+    /// `aggressiveness` runs from 0.0, only attacking near sure things, to 1.0, attacking at long
+    /// odds. At 0.5 it takes any attack more likely to win than not. Either way the odds it wants
+    /// stay between 5% and 95%, so it never passes up every attack, nor takes hopeless ones.
+    pub fn new(aggressiveness: f64) -> HeuristicAi {
+        HeuristicAi {
+            min_odds: (1.0 - aggressiveness).max(0.05).min(0.95),
+        }
+    }
+
+    pub fn for_difficulty(difficulty: Difficulty) -> HeuristicAi {
+        HeuristicAi::new(default_aggressiveness(difficulty))
+    }
+
+    /// How good `attack` is, or `None` if it isn't worth making.
+    fn score(&self, board: &Board, player: PlayerId, attack: Attack) -> Option<f64> {
+        let attacking = board.dice(attack.from);
+        let odds = win_probability(attacking, board.dice(attack.to));
+        // a full territory wastes any dice placed on it, so it may as well try its luck
+        if odds < self.min_odds && attacking < MAX_DICE {
+            return None;
+        }
+
+        let before = rules::largest_group(board, player);
+        let mut captured = board.clone();
+        captured.set_owner(attack.to, player);
+        let growth = rules::largest_group(&captured, player) as f64 - before as f64;
+        Some(odds + 0.1 * growth)
+    }
+}
+
+impl Strategy for HeuristicAi {
+    fn next_action(&mut self, board: &Board, player: PlayerId) -> Action {
+        let mut best: Option<(Attack, f64)> = None;
+        for attack in rules::legal_attacks(board, player) {
+            if let Some(score) = self.score(board, player, attack) {
+                if best.map_or(true, |(_, best)| score > best) {
+                    best = Some((attack, score));
+                }
+            }
+        }
+        match best {
+            Some((attack, _)) => Action::Attack(attack),
+            None => Action::EndTurn,
+        }
+    }
+}
+
+/// How willing each difficulty is to take risks.
+pub fn default_aggressiveness(difficulty: Difficulty) -> f64 {
+    match difficulty {
+        Difficulty::Easy => 0.7,
+        Difficulty::Normal => 0.45,
+        Difficulty::Hard => 0.4,
+    }
+}
@@ -0,0 +1,119 @@
+use amethyst::core::{
+    ecs::{Read, System, Write},
+    shrev::EventChannel,
+    timing::Time,
+};
+
+use super::mcts::{Budget, MctsAi, Position};
+use super::{HeuristicAi, Strategy};
+use crate::board::PlayerId;
+use crate::game::{Action, Game};
+use crate::players::{Controller, Players};
+use crate::setup::Difficulty;
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// Seconds the AI waits before each move, so people can follow what it does.
+const MOVE_DELAY: f32 = 0.6;
+
+/// How the gameplay state asks the AI for moves.
+#[derive(Default)]
+pub struct AiControl {
+    /// the AI player the gameplay state is waiting on, if any. Cleared once a move is sent.
+    pub waiting: Option<PlayerId>,
+    /// overrides how aggressive every AI plays, from 0.0 to 1.0
+    pub aggressiveness: Option<f64>,
+    /// how long the hard AI searches for each move
+    pub budget: Budget,
+    /// seeds the hard AI's searches, so a game with the same seed plays out the same way
+    pub seed: u64,
+}
+
+/// Plays the AI seats: once the gameplay state is waiting on an AI player, it picks their move
+/// after a short delay and sends it as an `Action` event.
+///
+/// The hard AI searches for its move on another thread, so the game keeps drawing frames while
+/// it thinks.
+#[derive(Default)]
+pub struct AiSystem {
+    timer: f32,
+    /// the player a search is running for, and where its move will arrive
+    search: Option<(PlayerId, Receiver<Action>)>,
+}
+
+impl<'s> System<'s> for AiSystem {
+    type SystemData = (
+        Read<'s, Time>,
+        Option<Read<'s, Game>>,
+        Option<Read<'s, Players>>,
+        Write<'s, AiControl>,
+        Write<'s, EventChannel<Action>>,
+    );
+
+    fn run(&mut self, (time, game, players, mut control, mut actions): Self::SystemData) {
+        let player = match control.waiting {
+            Some(player) => player,
+            None => {
+                // nobody is waiting on an unfinished search any more, so let it go
+                self.search = None;
+                self.timer = 0.0;
+                return;
+            }
+        };
+        self.timer += time.delta_seconds();
+        let (game, players) = match (game, players) {
+            (Some(game), Some(players)) => (game, players),
+            _ => return,
+        };
+
+        let difficulty = match players.get(player).map(|p| p.controller) {
+            Some(Controller::Ai(difficulty)) => difficulty,
+            _ => return,
+        };
+        let searching = self.search.as_ref().map_or(false, |(p, _)| *p == player);
+        if difficulty == Difficulty::Hard && !searching {
+            // start searching straight away, so the delay counts towards the thinking time. Each
+            // move gets its own seed from how far the game has got, which a save keeps.
+            let moves = game.history().len() as u64;
+            let seed = control.seed ^ moves.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            self.search = Some((player, spawn_search(&game, control.budget, seed)));
+        }
+        if self.timer < MOVE_DELAY {
+            return;
+        }
+
+        let mut heuristic = match control.aggressiveness {
+            Some(aggressiveness) => HeuristicAi::new(aggressiveness),
+            None => HeuristicAi::for_difficulty(difficulty),
+        };
+        let action = match self.search.take() {
+            Some((_, receiver)) => match receiver.try_recv() {
+                Ok(action) => action,
+                Err(TryRecvError::Empty) => {
+                    self.search = Some((player, receiver));
+                    return;
+                }
+                // the search thread died without a move, so play the heuristic one instead
+                Err(TryRecvError::Disconnected) => heuristic.next_action(game.board(), player),
+            },
+            None => heuristic.next_action(game.board(), player),
+        };
+
+        actions.single_write(action);
+        control.waiting = None;
+        self.timer = 0.0;
+    }
+}
+
+/// Search for the current player's move on another thread.
+fn spawn_search(game: &Game, budget: Budget, seed: u64) -> Receiver<Action> {
+    let position = Position::from_game(game);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let action = MctsAi::new(budget, seed).search(&position);
+        // the game may have moved on already, in which case nobody wants the move
+        let _ = sender.send(action);
+    });
+    receiver
+}
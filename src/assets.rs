@@ -16,12 +16,14 @@ use amethyst::{
 };
 
 use crate::config::GameSettings;
+use crate::players::Palette;
 
 #[derive(Clone)]
 pub struct HexAssets {
     pub spritesheet: Handle<SpriteSheet>,
     pub font: Handle<FontAsset>,
     pub settings: Handle<GameSettings>,
+    pub palette: Handle<Palette>,
     pub hex_sprites: Handle<SpriteSheet>,
     pub camera: Entity,
 }
@@ -0,0 +1,334 @@
+//! Plays AI players against each other without a window, and reports how each of them did.
+//!
+//! Maps are generated from the same settings file as the game, one seed after another, and the
+//! first turn passes round the table from game to game so no seat is always first. Searches run
+//! on an iteration budget only, so the same seed and players always give the same report.
+
+use hexadie::ai::{default_aggressiveness, Budget, HeuristicAi, MctsAi, Position, Strategy};
+use hexadie::board::PlayerId;
+use hexadie::config::GameSettings;
+use hexadie::game::{Action, Game};
+use hexadie::mapgen;
+use hexadie::seed::MapSeed;
+use hexadie::setup::Difficulty;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "usage: hexadie-sim [options] <player> <player> [<player>...]
+
+players:
+    easy, normal        the heuristic AI at that difficulty's aggressiveness
+    hard                the tree search AI, with the settings' iteration budget
+    heuristic=<0.0-1.0> the heuristic AI with the given aggressiveness
+    mcts=<iterations>   the tree search AI with the given iteration budget
+
+options:
+    --games <n>         how many games to play (default 1000)
+    --seed <hex>        the first map seed; each game uses the next one (default: the settings'
+                        seed, or a random one)
+    --config <file>     the settings to generate maps from (default assets/config.ron)
+    --csv <file>        also write one line per game to a CSV file";
+
+/// A game still going after this many actions is called a draw.
+const MAX_ACTIONS: u32 = 50_000;
+
+/// Who plays a seat.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Controller {
+    Heuristic(f64),
+    Mcts(u32),
+}
+
+impl Controller {
+    fn parse(text: &str, settings: &GameSettings) -> Result<Controller, String> {
+        let hard = settings
+            .ai_iterations
+            .unwrap_or(Budget::default().iterations);
+        let (kind, value) = match text.find('=') {
+            Some(at) => (&text[..at], Some(&text[at + 1..])),
+            None => (text, None),
+        };
+        let controller = match (kind, value) {
+            ("easy", None) => Controller::Heuristic(default_aggressiveness(Difficulty::Easy)),
+            ("normal", None) => Controller::Heuristic(default_aggressiveness(Difficulty::Normal)),
+            ("hard", None) => Controller::Mcts(hard),
+            ("heuristic", Some(value)) => match value.parse::<f64>() {
+                Ok(aggressiveness) if aggressiveness >= 0.0 && aggressiveness <= 1.0 => {
+                    Controller::Heuristic(aggressiveness)
+                }
+                _ => return Err(format!("bad aggressiveness in {:?}", text)),
+            },
+            ("mcts", Some(value)) => match value.parse::<u32>() {
+                Ok(iterations) if iterations > 0 => Controller::Mcts(iterations),
+                _ => return Err(format!("bad iteration count in {:?}", text)),
+            },
+            _ => return Err(format!("unknown player {:?}", text)),
+        };
+        Ok(controller)
+    }
+
+    /// `seed` seeds the tree search, so it should differ from move to move.
+    fn next_action(self, game: &Game, seed: u64) -> Action {
+        match self {
+            Controller::Heuristic(aggressiveness) => {
+                HeuristicAi::new(aggressiveness).next_action(game.board(), game.current_player())
+            }
+            Controller::Mcts(iterations) => {
+                let budget = Budget {
+                    iterations,
+                    time: None,
+                };
+                MctsAi::new(budget, seed).search(&Position::from_game(game))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Controller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Controller::Heuristic(aggressiveness) => write!(f, "heuristic={}", aggressiveness),
+            Controller::Mcts(iterations) => write!(f, "mcts={}", iterations),
+        }
+    }
+}
+
+struct Options {
+    games: u32,
+    seed: Option<MapSeed>,
+    config: PathBuf,
+    csv: Option<PathBuf>,
+    players: Vec<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        games: 1000,
+        seed: None,
+        config: PathBuf::from("assets/config.ron"),
+        csv: None,
+        players: vec![],
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--games" => {
+                options.games = value("--games")?
+                    .parse()
+                    .map_err(|_| "--games needs a number".to_string())?;
+            }
+            "--seed" => {
+                let seed = value("--seed")?
+                    .parse::<MapSeed>()
+                    .map_err(|_| "--seed needs a hex number".to_string())?;
+                options.seed = Some(seed);
+            }
+            "--config" => options.config = PathBuf::from(value("--config")?),
+            "--csv" => options.csv = Some(PathBuf::from(value("--csv")?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => options.players.push(arg),
+        }
+    }
+    if options.players.len() < 2 {
+        return Err("a game needs at least two players".to_string());
+    }
+    Ok(options)
+}
+
+/// How one game went. Player ids are seat numbers.
+struct GameResult {
+    seed: MapSeed,
+    first: PlayerId,
+    /// nobody, if the game was a draw
+    winner: Option<PlayerId>,
+    rounds: u32,
+    actions: u32,
+}
+
+fn play(
+    settings: &GameSettings,
+    controllers: &[Controller],
+    seed: MapSeed,
+    first: usize,
+) -> GameResult {
+    let count = controllers.len();
+    let board = mapgen::generate(settings, &mut seed.rng()).board;
+    let order = (0..count).map(|i| PlayerId((first + i) % count)).collect();
+    let mut game = Game::new(board, order, seed.game_rng());
+
+    let mut actions = 0;
+    while game.board().players().len() > 1 && actions < MAX_ACTIONS {
+        let player = game.current_player();
+        let search_seed = seed.0 ^ (actions as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let action = controllers[player.0].next_action(&game, search_seed);
+        game.apply(action)
+            .expect("AI players only make legal moves");
+        actions += 1;
+    }
+
+    let survivors = game.board().players();
+    GameResult {
+        seed,
+        first: PlayerId(first),
+        winner: if survivors.len() == 1 {
+            Some(survivors[0])
+        } else {
+            None
+        },
+        rounds: game.turn(),
+        actions,
+    }
+}
+
+fn load_settings(path: &PathBuf) -> Result<GameSettings, String> {
+    let file = File::open(path).map_err(|e| format!("can't open {}: {}", path.display(), e))?;
+    ron::de::from_reader(file).map_err(|e| format!("can't read {}: {}", path.display(), e))
+}
+
+fn write_csv(path: &PathBuf, controllers: &[Controller], results: &[GameResult]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(
+        file,
+        "game,seed,first_seat,winner_seat,winner,rounds,actions"
+    )?;
+    for (i, result) in results.iter().enumerate() {
+        let (seat, winner) = match result.winner {
+            Some(winner) => (
+                (winner.0 + 1).to_string(),
+                controllers[winner.0].to_string(),
+            ),
+            None => (String::new(), "draw".to_string()),
+        };
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{}",
+            i + 1,
+            result.seed,
+            result.first.0 + 1,
+            seat,
+            winner,
+            result.rounds,
+            result.actions
+        )?;
+    }
+    Ok(())
+}
+
+fn report(settings: &GameSettings, controllers: &[Controller], results: &[GameResult]) {
+    let count = controllers.len();
+    let games = results.len().max(1) as f64;
+    let decided: Vec<&GameResult> = results.iter().filter(|r| r.winner.is_some()).collect();
+    let rounds: u32 = results.iter().map(|r| r.rounds).sum();
+    let actions: u32 = results.iter().map(|r| r.actions).sum();
+
+    println!();
+    println!(
+        "{} games of {} players on {}x{} maps, {} drawn",
+        results.len(),
+        count,
+        settings.width,
+        settings.height,
+        results.len() - decided.len()
+    );
+    println!(
+        "average length: {:.1} rounds, {:.1} actions",
+        rounds as f64 / games,
+        actions as f64 / games
+    );
+    println!();
+    println!("seat  player            wins  win rate");
+    for (seat, controller) in controllers.iter().enumerate() {
+        let wins = decided
+            .iter()
+            .filter(|r| r.winner == Some(PlayerId(seat)))
+            .count();
+        println!(
+            "{:<4}  {:<16}  {:>4}  {:>7.1}%",
+            seat + 1,
+            controller.to_string(),
+            wins,
+            100.0 * wins as f64 / games
+        );
+    }
+    println!();
+    let first_wins = decided.iter().filter(|r| r.winner == Some(r.first)).count();
+    println!(
+        "the first player won {:.1}% of decided games ({:.1}% would be no advantage)",
+        100.0 * first_wins as f64 / decided.len().max(1) as f64,
+        100.0 / count as f64
+    );
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let mut settings = match load_settings(&options.config) {
+        Ok(settings) => settings,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    };
+    let controllers = match options
+        .players
+        .iter()
+        .map(|player| Controller::parse(player, &settings))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(controllers) => controllers,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    settings.player_count = Some(controllers.len() as u32);
+
+    let first_seed = options
+        .seed
+        .or(settings.seed.map(MapSeed))
+        .unwrap_or_else(MapSeed::random);
+    println!(
+        "playing {} games from seed {}: {}",
+        options.games,
+        first_seed,
+        controllers
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(" vs ")
+    );
+
+    let results: Vec<GameResult> = (0..options.games)
+        .map(|i| {
+            let seed = MapSeed(first_seed.0.wrapping_add(i as u64));
+            play(
+                &settings,
+                &controllers,
+                seed,
+                i as usize % controllers.len(),
+            )
+        })
+        .collect();
+
+    if let Some(path) = &options.csv {
+        if let Err(e) = write_csv(path, &controllers, &results) {
+            eprintln!("can't write {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+    report(&settings, &controllers, &results);
+}
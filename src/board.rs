@@ -0,0 +1,226 @@
+//! The game board as plain data, with no dependency on the ECS.
+//! Generation, rules and AI all work on a `Board`; the renderer only mirrors it.
+
+use crate::hex::{Hex, Offset};
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies one of the players (seats) in a game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct PlayerId(pub usize);
+
+/// Identifies a territory, which is also its index into the board's territory list.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct TerritoryId(pub usize);
+
+/// A group of hexes owned by a single player, holding a stack of dice.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Territory {
+    pub id: TerritoryId,
+    pub owner: PlayerId,
+    pub dice: u32,
+    hexes: Vec<Hex>,
+    neighbors: Vec<TerritoryId>,
+}
+
+impl Territory {
+    /// Every hex in this territory.
+    pub fn hexes(&self) -> &[Hex] {
+        &self.hexes
+    }
+    pub fn size(&self) -> usize {
+        self.hexes.len()
+    }
+    /// Adjacent territories, sorted by id. Only valid after `Board::rebuild_adjacency`.
+    pub fn neighbors(&self) -> &[TerritoryId] {
+        &self.neighbors
+    }
+}
+
+/// A width x height grid of hexes, each of which may belong to a territory.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Board {
+    width: u32,
+    height: u32,
+    cells: Vec<Option<TerritoryId>>,
+    territories: Vec<Territory>,
+}
+
+impl Board {
+    pub fn new(width: u32, height: u32) -> Board {
+        Board {
+            width,
+            height,
+            cells: vec![None; (width * height) as usize],
+            territories: vec![],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, hex: Hex) -> bool {
+        let Offset { col, row } = hex.into();
+        col >= 0 && row >= 0 && (col as u32) < self.width && (row as u32) < self.height
+    }
+
+    /// cells are stored row by row
+    fn index(&self, hex: Hex) -> usize {
+        let Offset { col, row } = hex.into();
+        (row as u32 * self.width + col as u32) as usize
+    }
+
+    /// The territory `hex` belongs to, if any.
+    pub fn territory_at(&self, hex: Hex) -> Option<TerritoryId> {
+        if self.in_bounds(hex) {
+            self.cells[self.index(hex)]
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over every cell on the board as (hex, territory).
+    pub fn cells<'a>(&'a self) -> impl Iterator<Item = (Hex, Option<TerritoryId>)> + 'a {
+        let width = self.width as i32;
+        self.cells.iter().enumerate().map(move |(i, t)| {
+            let i = i as i32;
+            (Offset::new(i % width, i / width).into(), *t)
+        })
+    }
+
+    /// Create a new, empty territory.
+    pub fn add_territory(&mut self, owner: PlayerId, dice: u32) -> TerritoryId {
+        let id = TerritoryId(self.territories.len());
+        self.territories.push(Territory {
+            id,
+            owner,
+            dice,
+            hexes: vec![],
+            neighbors: vec![],
+        });
+        id
+    }
+
+    /// Place `hex` into a territory, removing it from any previous one.
+    pub fn assign(&mut self, hex: Hex, territory: TerritoryId) {
+        assert!(self.in_bounds(hex), "hex {:?} is off the board", hex);
+        let index = self.index(hex);
+        if let Some(previous) = self.cells[index] {
+            self.territories[previous.0].hexes.retain(|&h| h != hex);
+        }
+        self.cells[index] = Some(territory);
+        self.territories[territory.0].hexes.push(hex);
+    }
+
+    /// Recompute which territories border each other from the hexes they contain.
+    pub fn rebuild_adjacency(&mut self) {
+        let mut adjacency = vec![vec![]; self.territories.len()];
+        for (hex, territory) in self.cells() {
+            let territory = match territory {
+                Some(t) => t,
+                None => continue,
+            };
+            for neighbor in self.neighbor_cells(hex) {
+                if let Some(other) = self.territory_at(neighbor) {
+                    if other != territory {
+                        adjacency[territory.0].push(other);
+                    }
+                }
+            }
+        }
+        for (territory, mut neighbors) in self.territories.iter_mut().zip(adjacency) {
+            neighbors.sort();
+            neighbors.dedup();
+            territory.neighbors = neighbors;
+        }
+    }
+
+    /// The neighbors of `hex` that are on the board.
+    pub fn neighbor_cells<'a>(&'a self, hex: Hex) -> impl Iterator<Item = Hex> + 'a {
+        hex.neighbors()
+            .to_vec()
+            .into_iter()
+            .filter(move |&neighbor| self.in_bounds(neighbor))
+    }
+
+    pub fn territory(&self, id: TerritoryId) -> &Territory {
+        &self.territories[id.0]
+    }
+
+    pub fn territories(&self) -> impl Iterator<Item = &Territory> {
+        self.territories.iter()
+    }
+
+    pub fn territory_count(&self) -> usize {
+        self.territories.len()
+    }
+
+    /// All territories owned by `player`.
+    pub fn territories_of<'a>(&'a self, player: PlayerId) -> impl Iterator<Item = &'a Territory> + 'a {
+        self.territories.iter().filter(move |t| t.owner == player)
+    }
+
+    pub fn are_adjacent(&self, a: TerritoryId, b: TerritoryId) -> bool {
+        self.territory(a).neighbors.binary_search(&b).is_ok()
+    }
+
+    pub fn owner(&self, id: TerritoryId) -> PlayerId {
+        self.territory(id).owner
+    }
+
+    pub fn set_owner(&mut self, id: TerritoryId, owner: PlayerId) {
+        self.territories[id.0].owner = owner;
+    }
+
+    pub fn dice(&self, id: TerritoryId) -> u32 {
+        self.territory(id).dice
+    }
+
+    pub fn set_dice(&mut self, id: TerritoryId, dice: u32) {
+        self.territories[id.0].dice = dice;
+    }
+
+    /// Split the territories accepted by `include` into groups that are connected through
+    /// adjacent territories which are also accepted. Groups are returned largest first.
+    pub fn connected_groups<F>(&self, include: F) -> Vec<Vec<TerritoryId>>
+    where
+        F: Fn(&Territory) -> bool,
+    {
+        let mut visited = vec![false; self.territories.len()];
+        let mut groups = vec![];
+        for start in self.territories.iter().filter(|t| include(t)) {
+            if visited[start.id.0] {
+                continue;
+            }
+            visited[start.id.0] = true;
+            let mut group = vec![];
+            let mut stack = vec![start.id];
+            while let Some(id) = stack.pop() {
+                group.push(id);
+                for &neighbor in self.territory(id).neighbors() {
+                    if !visited[neighbor.0] && include(self.territory(neighbor)) {
+                        visited[neighbor.0] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            group.sort();
+            groups.push(group);
+        }
+        groups.sort_by(|a, b| b.len().cmp(&a.len()));
+        groups
+    }
+
+    /// Every player that owns at least one territory, in id order.
+    pub fn players(&self) -> Vec<PlayerId> {
+        let mut players: Vec<PlayerId> = self.territories.iter().map(|t| t.owner).collect();
+        players.sort();
+        players.dedup();
+        players
+    }
+}
@@ -0,0 +1,223 @@
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    core::{
+        ecs::{Component, DenseVecStorage, Entity},
+        transform::Transform,
+    },
+    prelude::*,
+    renderer::{
+        loaders::load_from_srgba,
+        palette::{Srgb, Srgba},
+        rendy::mesh::{MeshBuilder, Normal, Position, Tangent, TexCoord},
+        types::{MeshData, TextureData},
+        Material, MaterialDefaults, Mesh, Texture,
+    },
+};
+
+use crate::board::{Board, PlayerId, TerritoryId};
+use crate::dice_view::DiceView;
+use crate::hex::Layout;
+use crate::players::Players;
+use crate::polygon::{self, Triangle};
+
+use std::collections::HashMap;
+
+const BORDER: (f32, f32, f32) = (0.1, 0.1, 0.1);
+const BORDER_WIDTH: f32 = 4.0;
+
+/// Mirrors a `Board` into entities: one filled polygon per territory, the borders around them,
+/// and the dice on top.
+///
+/// The shapes never change once spawned, so syncing only swaps the material of territories whose
+/// owner or highlight changed.
+pub struct BoardView {
+    /// one entity per territory, indexed by territory id
+    territories: Vec<Entity>,
+    /// the owner each territory was last drawn for, and whether it was highlighted
+    drawn: Vec<(PlayerId, bool)>,
+    borders: Entity,
+    dice: DiceView,
+    materials: HashMap<(PlayerId, bool), Handle<Material>>,
+    /// a territory drawn lighter than the rest, to show it is selected
+    highlighted: Option<TerritoryId>,
+}
+
+impl BoardView {
+    /// Spawn a polygon for every territory on the board, their borders, and their dice.
+    pub fn spawn(world: &mut World, board: &Board) -> BoardView {
+        BoardView::spawn_with_layout(world, board, &Layout::BOARD)
+    }
+
+    /// Spawn the board as `spawn` does, but laid out by `layout`, which may move or shrink it.
+    pub fn spawn_with_layout(world: &mut World, board: &Board, layout: &Layout) -> BoardView {
+        world.register::<Player>();
+        world.register::<Area>();
+
+        let scale = layout.spacing.1 / Layout::BOARD.spacing.1;
+        let mut materials = HashMap::new();
+        let mut territories = vec![];
+        let mut drawn = vec![];
+        let mut outlines = vec![];
+        for territory in board.territories() {
+            let mesh = build_mesh(world, &polygon::territory_fill(board, layout, territory.id));
+            let material = materials
+                .entry((territory.owner, false))
+                .or_insert_with(|| build_material(world, player_color(world, territory.owner)))
+                .clone();
+
+            let entity = world
+                .create_entity()
+                .with(mesh)
+                .with(material)
+                .with(Transform::default())
+                .with(Area {
+                    id: territory.id.0,
+                })
+                .with(Player(territory.owner))
+                .build();
+            territories.push(entity);
+            drawn.push((territory.owner, false));
+            outlines.extend(polygon::territory_outline(board, layout, territory.id));
+        }
+
+        // every border is drawn once per side; both copies land in the same place
+        let (r, g, b) = BORDER;
+        let border_mesh = build_mesh(world, &polygon::stroke(&outlines, BORDER_WIDTH * scale));
+        let border_material = build_material(world, Srgb::new(r, g, b));
+        let mut transform = Transform::default();
+        transform.set_translation_z(0.1);
+        let borders = world
+            .create_entity()
+            .with(border_mesh)
+            .with(border_material)
+            .with(transform)
+            .build();
+        let dice = DiceView::spawn(world, board, layout);
+
+        BoardView {
+            territories,
+            drawn,
+            borders,
+            dice,
+            materials,
+            highlighted: None,
+        }
+    }
+
+    /// Recolor the territories whose owner or highlight changed since they were last drawn, and
+    /// restack any dice that changed.
+    pub fn sync(&mut self, world: &mut World, board: &Board) {
+        self.dice.sync(world, board);
+        for territory in board.territories() {
+            let wanted = (territory.owner, Some(territory.id) == self.highlighted);
+            if self.drawn[territory.id.0] == wanted {
+                continue;
+            }
+            let material = match self.materials.get(&wanted) {
+                Some(material) => material.clone(),
+                None => {
+                    let mut color = player_color(world, territory.owner);
+                    if wanted.1 {
+                        color = lighten(color);
+                    }
+                    let material = build_material(world, color);
+                    self.materials.insert(wanted, material.clone());
+                    material
+                }
+            };
+
+            let entity = self.territories[territory.id.0];
+            world
+                .write_storage::<Handle<Material>>()
+                .insert(entity, material)
+                .expect("board view entity was deleted");
+            world
+                .write_storage::<Player>()
+                .insert(entity, Player(territory.owner))
+                .expect("board view entity was deleted");
+            self.drawn[territory.id.0] = wanted;
+        }
+    }
+
+    /// Draw `territory` lighter than the rest of the board, or stop highlighting with `None`.
+    pub fn set_highlight(&mut self, world: &mut World, board: &Board, territory: Option<TerritoryId>) {
+        self.highlighted = territory;
+        self.sync(world, board);
+    }
+
+    /// Remove every entity this view created.
+    pub fn despawn(self, world: &mut World) {
+        self.dice.despawn(world);
+        let mut entities = self.territories;
+        entities.push(self.borders);
+        world
+            .delete_entities(&entities)
+            .expect("board view entity was deleted twice");
+    }
+}
+
+/// Upload a flat mesh made of `triangles`.
+fn build_mesh(world: &World, triangles: &[Triangle]) -> Handle<Mesh> {
+    let positions: Vec<Position> = triangles
+        .iter()
+        .flat_map(|triangle| triangle.iter())
+        .map(|&(x, y)| Position([x, y, 0.0]))
+        .collect();
+    let count = positions.len();
+    let mesh: MeshData = MeshBuilder::new()
+        .with_vertices(positions)
+        .with_vertices(vec![Normal([0.0, 0.0, 1.0]); count])
+        .with_vertices(vec![Tangent([1.0, 0.0, 0.0, 1.0]); count])
+        .with_vertices(vec![TexCoord([0.0, 0.0]); count])
+        .into();
+
+    let loader = world.read_resource::<Loader>();
+    loader.load_from_data(mesh, (), &world.read_resource::<AssetStorage<Mesh>>())
+}
+
+/// A material that is a single flat color.
+fn build_material(world: &World, color: Srgb) -> Handle<Material> {
+    let loader = world.read_resource::<Loader>();
+    let texture: TextureData =
+        load_from_srgba(Srgba::new(color.red, color.green, color.blue, 1.0)).into();
+    let albedo: Handle<Texture> =
+        loader.load_from_data(texture, (), &world.read_resource::<AssetStorage<Texture>>());
+    let defaults = world.read_resource::<MaterialDefaults>().0.clone();
+    loader.load_from_data(
+        Material { albedo, ..defaults },
+        (),
+        &world.read_resource::<AssetStorage<Material>>(),
+    )
+}
+
+/// The color `player` is drawn in, from the `Players` resource.
+pub fn player_color(world: &World, player: PlayerId) -> Srgb {
+    let (r, g, b) = world.read_resource::<Players>().color(player);
+    Srgb::new(r, g, b)
+}
+
+/// Blend a color halfway to white.
+fn lighten(color: Srgb) -> Srgb {
+    Srgb::new(
+        (color.red + 1.0) / 2.0,
+        (color.green + 1.0) / 2.0,
+        (color.blue + 1.0) / 2.0,
+    )
+}
+
+/// The Player component records which player an entity (just territories for now) belongs to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Player(pub PlayerId);
+
+impl Component for Player {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The Area component stores the id of the territory an entity draws.
+pub struct Area {
+    pub id: usize,
+}
+
+impl Component for Area {
+    type Storage = DenseVecStorage<Self>;
+}
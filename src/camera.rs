@@ -0,0 +1,68 @@
+//! Panning and zooming the 2D camera made by `entities::create_camera`.
+
+use amethyst::{
+    core::{
+        ecs::{Join, Read, ReadStorage, System, WriteStorage},
+        timing::Time,
+        transform::Transform,
+    },
+    input::{InputHandler, StringBindings, VirtualKeyCode},
+    renderer::Camera,
+};
+
+use crate::picking::HexPicking;
+
+/// World units per second the camera moves at normal zoom.
+const PAN_SPEED: f32 = 800.0;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 2.0;
+
+/// WASD pans the camera, the mouse wheel zooms it.
+///
+/// Zooming scales the camera's transform, so anything converting between the screen and the
+/// world through the camera (like hex picking) keeps working. The camera only moves while hex
+/// picking is on, so typing elsewhere doesn't drag the view around.
+pub struct CameraControlSystem;
+
+impl<'s> System<'s> for CameraControlSystem {
+    type SystemData = (
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, Time>,
+        Read<'s, HexPicking>,
+        ReadStorage<'s, Camera>,
+        WriteStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, (input, time, picking, cameras, mut transforms): Self::SystemData) {
+        if picking.layout.is_none() {
+            return;
+        }
+        let mut pan = (0.0, 0.0);
+        if input.key_is_down(VirtualKeyCode::A) {
+            pan.0 -= 1.0;
+        }
+        if input.key_is_down(VirtualKeyCode::D) {
+            pan.0 += 1.0;
+        }
+        if input.key_is_down(VirtualKeyCode::S) {
+            pan.1 -= 1.0;
+        }
+        if input.key_is_down(VirtualKeyCode::W) {
+            pan.1 += 1.0;
+        }
+        let wheel = input.mouse_wheel_value(false);
+
+        for (_camera, transform) in (&cameras, &mut transforms).join() {
+            let zoom = transform.scale().x;
+            let step = PAN_SPEED * zoom * time.delta_seconds();
+            transform.prepend_translation_x(pan.0 * step);
+            transform.prepend_translation_y(pan.1 * step);
+
+            if wheel != 0.0 {
+                // scrolling up zooms in, which means showing less of the world
+                let zoom = (zoom * (1.0 - wheel * 0.1)).max(MIN_ZOOM).min(MAX_ZOOM);
+                transform.set_scale([zoom, zoom, 1.0].into());
+            }
+        }
+    }
+}
@@ -9,12 +9,31 @@ use amethyst::{
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+use crate::assets::HexAssets;
+
+/// The settings read from `config.ron`.
+///
+/// While a game is played, the settings its map was generated from are also kept as a resource of
+/// their own, so saves, replays and rematches don't depend on what `config.ron` says by then.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Default)]
 pub struct GameSettings {
     pub width: u32,
     pub height: u32,
     pub area_size: u32,
     pub player_count: Option<u32>,
+    /// generate the same map every time, instead of a random one
+    pub seed: Option<u64>,
+    /// how fast attacks play out; 2.0 is twice as fast as normal
+    pub animation_speed: Option<f32>,
+    /// resolve attacks instantly, without rolling any dice on screen
+    pub skip_animations: Option<bool>,
+    /// how readily AI players attack, from 0.0 (only sure things) to 1.0 (anything at all)
+    pub ai_aggressiveness: Option<f64>,
+    /// how many positions the hard AI looks at before each move
+    pub ai_iterations: Option<u32>,
+    /// the most seconds the hard AI thinks about each move; setting this means the same seed may
+    /// not play out the same way twice
+    pub ai_think_time: Option<f32>,
 }
 
 impl Asset for GameSettings {
@@ -37,3 +56,13 @@ impl<'a, 'b> SystemBundle<'a, 'b> for GameSettingsBundle {
         Ok(())
     }
 }
+
+/// The settings in `config.ron`. The assets must have finished loading.
+pub fn load_settings(world: &World) -> GameSettings {
+    let assets = world.read_resource::<HexAssets>();
+    let asset_storage = world.read_resource::<AssetStorage<GameSettings>>();
+    asset_storage
+        .get(&assets.settings)
+        .expect("failed to load settings")
+        .clone()
+}
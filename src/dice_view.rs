@@ -0,0 +1,218 @@
+//! Stacks of dice drawn on top of each territory.
+
+use amethyst::{
+    assets::Handle,
+    core::{
+        ecs::{Component, DenseVecStorage, Entity, Join, ReadExpect, ReadStorage, System, WriteStorage},
+        math::Point3,
+        transform::Transform,
+    },
+    prelude::*,
+    renderer::{resources::Tint, Camera, SpriteRender, SpriteSheet, Transparent},
+    ui::{Anchor, FontAsset, UiText, UiTransform},
+    window::ScreenDimensions,
+};
+
+use crate::assets::HexAssets;
+use crate::board::{Board, PlayerId, Territory};
+use crate::board_view::player_color;
+use crate::hex::Layout;
+
+/// More dice than this are drawn as a single die with a number next to it.
+const STACK_LIMIT: u32 = 4;
+/// The dice sprites are 64 pixels square, which is bigger than a hex.
+const DIE_SCALE: f32 = 0.4;
+/// How far each die in a stack sits above the one below it.
+const DIE_STEP: f32 = 8.0;
+const DICE_Z: f32 = 0.2;
+
+/// Mirrors the dice on a `Board` into sprites, one stack per territory.
+pub struct DiceView {
+    /// indexed by territory id
+    stacks: Vec<Stack>,
+    spritesheet: Handle<SpriteSheet>,
+    font: Handle<FontAsset>,
+    /// the size the dice are drawn at, relative to the game board's
+    scale: f32,
+}
+
+struct Stack {
+    anchor: (f32, f32),
+    /// the owner and dice count the stack was last drawn for
+    drawn: (PlayerId, u32),
+    entities: Vec<Entity>,
+}
+
+impl DiceView {
+    /// Spawn a stack of dice for every territory on the board.
+    pub fn spawn(world: &mut World, board: &Board, layout: &Layout) -> DiceView {
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+        world.register::<BoardLabel>();
+
+        let mut view = DiceView {
+            stacks: vec![],
+            spritesheet: assets.spritesheet,
+            font: assets.font,
+            scale: layout.spacing.1 / Layout::BOARD.spacing.1,
+        };
+        for territory in board.territories() {
+            let mut stack = Stack {
+                anchor: anchor(layout, territory),
+                drawn: (territory.owner, territory.dice),
+                entities: vec![],
+            };
+            view.build(world, &mut stack, territory.id.0);
+            view.stacks.push(stack);
+        }
+        view
+    }
+
+    /// Redraw the stacks whose dice count or owner changed since they were last drawn.
+    pub fn sync(&mut self, world: &mut World, board: &Board) {
+        let mut stacks = std::mem::replace(&mut self.stacks, vec![]);
+        for (stack, territory) in stacks.iter_mut().zip(board.territories()) {
+            let wanted = (territory.owner, territory.dice);
+            if stack.drawn == wanted {
+                continue;
+            }
+            world
+                .delete_entities(&stack.entities)
+                .expect("dice view entity was deleted twice");
+            stack.entities.clear();
+            stack.drawn = wanted;
+            self.build(world, stack, territory.id.0);
+        }
+        self.stacks = stacks;
+    }
+
+    /// Remove every entity this view created.
+    pub fn despawn(self, world: &mut World) {
+        let entities: Vec<Entity> = self
+            .stacks
+            .into_iter()
+            .flat_map(|stack| stack.entities)
+            .collect();
+        world
+            .delete_entities(&entities)
+            .expect("dice view entity was deleted twice");
+    }
+
+    /// Create the entities for one stack, as described by its `drawn` owner and count.
+    fn build(&self, world: &mut World, stack: &mut Stack, seed: usize) {
+        let (owner, count) = stack.drawn;
+        let (x, y) = stack.anchor;
+        let stacked = if count > STACK_LIMIT { 1 } else { count };
+        let color = player_color(world, owner);
+        let (die_scale, die_step) = (DIE_SCALE * self.scale, DIE_STEP * self.scale);
+        for i in 0..stacked {
+            let sprite_render = SpriteRender {
+                sprite_sheet: self.spritesheet.clone(),
+                // vary the faces, but keep them the same every time the stack is drawn
+                sprite_number: (seed + i as usize) % 6,
+            };
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(x, y + i as f32 * die_step, DICE_Z + i as f32 * 0.01);
+            transform.set_scale([die_scale, die_scale, 1.0].into());
+            let die = world
+                .create_entity()
+                .with(sprite_render)
+                .with(transform)
+                .with(Tint(color.into()))
+                .with(Transparent)
+                .build();
+            stack.entities.push(die);
+        }
+
+        if count > STACK_LIMIT {
+            let offset = 64.0 * die_scale;
+            let transform = UiTransform::new(
+                "dice badge".to_string(),
+                Anchor::TopLeft,
+                Anchor::Middle,
+                0.0,
+                0.0,
+                1.0,
+                40.0,
+                24.0,
+            );
+            let badge = world
+                .create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    self.font.clone(),
+                    count.to_string(),
+                    [1.0, 1.0, 1.0, 1.0],
+                    20.0,
+                ))
+                .with(BoardLabel {
+                    x: x + offset,
+                    y,
+                })
+                .build();
+            stack.entities.push(badge);
+        }
+    }
+}
+
+/// The center of the territory's hex nearest the middle of the territory.
+///
+/// Unlike the middle itself, this is always inside the territory, and it stays put as long as the
+/// territory keeps its hexes.
+fn anchor(layout: &Layout, territory: &Territory) -> (f32, f32) {
+    let centers: Vec<(f32, f32)> = territory
+        .hexes()
+        .iter()
+        .map(|&hex| layout.hex_to_pixel(hex))
+        .collect();
+    let count = centers.len() as f32;
+    let middle = (
+        centers.iter().map(|c| c.0).sum::<f32>() / count,
+        centers.iter().map(|c| c.1).sum::<f32>() / count,
+    );
+    let distance = |c: &(f32, f32)| (c.0 - middle.0).powi(2) + (c.1 - middle.1).powi(2);
+    centers
+        .into_iter()
+        .min_by(|a, b| distance(a).partial_cmp(&distance(b)).expect("hex centers are finite"))
+        .unwrap_or(middle)
+}
+
+/// Text drawn in the UI, pinned to a point on the board: the count on a tall stack of dice, or the
+/// label under a map preview.
+pub struct BoardLabel {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Component for BoardLabel {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Keeps each `BoardLabel` over its point on the board as the camera pans and zooms.
+pub struct BoardLabelSystem;
+
+impl<'s> System<'s> for BoardLabelSystem {
+    type SystemData = (
+        ReadExpect<'s, ScreenDimensions>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, BoardLabel>,
+        WriteStorage<'s, UiTransform>,
+    );
+
+    fn run(&mut self, (dimensions, cameras, transforms, labels, mut ui_transforms): Self::SystemData) {
+        let (camera, camera_transform) = match (&cameras, &transforms).join().next() {
+            Some(camera) => camera,
+            None => return,
+        };
+        for (label, ui_transform) in (&labels, &mut ui_transforms).join() {
+            let screen = camera.projection().world_to_screen(
+                Point3::new(label.x, label.y, 0.0),
+                dimensions.diagonal(),
+                camera_transform,
+            );
+            // screen coordinates grow downwards, ui coordinates grow upwards
+            ui_transform.local_x = screen.x;
+            ui_transform.local_y = -screen.y;
+        }
+    }
+}
@@ -1,10 +1,16 @@
 use amethyst::{
     assets::Handle,
-    core::{ecs::Entity, transform::Transform},
+    core::{
+        ecs::{Entity, Join, ReadStorage, WriteStorage},
+        transform::Transform,
+    },
     prelude::*,
     renderer::{palette::Srgb, resources::Tint, Camera, SpriteRender, SpriteSheet, Transparent},
 };
 
+use crate::board::Board;
+use crate::hex::Layout;
+
 use std::f32::consts::PI;
 
 pub fn create_sprite(
@@ -51,3 +57,25 @@ pub fn create_camera(world: &mut World) -> Entity {
         .with(transform)
         .build()
 }
+
+/// Point the camera at the middle of the board, undoing any pan or zoom.
+pub fn focus_camera(world: &mut World, board: &Board) {
+    let (spacing_x, spacing_y) = Layout::BOARD.spacing;
+    let center = (
+        board.width() as f32 * spacing_x / 2.0,
+        board.height() as f32 * spacing_y / 2.0,
+    );
+    point_camera(world, center.0, center.1);
+}
+
+/// Point the camera at (x, y), undoing any pan or zoom.
+pub fn point_camera(world: &mut World, x: f32, y: f32) {
+    world.exec(
+        |(mut transforms, cameras): (WriteStorage<Transform>, ReadStorage<Camera>)| {
+            for (transform, _camera) in (&mut transforms, &cameras).join() {
+                transform.set_translation_xyz(x, y, 1.0);
+                transform.set_scale([1.0, 1.0, 1.0].into());
+            }
+        },
+    );
+}
@@ -0,0 +1,193 @@
+//! A game in progress: the board, whose turn it is, and the dice each player has in reserve.
+
+use crate::board::{Board, PlayerId};
+use crate::rules::{self, Attack, AttackError, AttackOutcome, Reinforcement};
+use crate::seed::GameRng;
+
+use serde::{Deserialize, Serialize};
+
+/// Something a player can do on their turn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Action {
+    Attack(Attack),
+    EndTurn,
+}
+
+/// What happened when an action was applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActionOutcome {
+    Attack(AttackOutcome),
+    /// the turn passed on after the player who ended it was reinforced
+    EndTurn(Reinforcement),
+}
+
+/// How a player has done so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PlayerStats {
+    pub attacks: u32,
+    /// attacks that took the territory
+    pub captures: u32,
+    /// the most territories the player has had connected at once
+    pub largest_group: usize,
+    /// the round the player lost their last territory, if they have
+    pub eliminated: Option<u32>,
+}
+
+impl PlayerStats {
+    /// The share of attacks that took the territory, from 0.0 to 1.0.
+    pub fn success_rate(&self) -> f64 {
+        if self.attacks == 0 {
+            0.0
+        } else {
+            self.captures as f64 / self.attacks as f64
+        }
+    }
+}
+
+/// The state of a game, independent of how it is shown. It can be saved and loaded with serde,
+/// and carries on exactly as it would have.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Game {
+    board: Board,
+    /// every seat, in turn order
+    players: Vec<PlayerId>,
+    /// index into `players` of whoever's turn it is
+    current: usize,
+    turn: u32,
+    /// reserve dice, indexed by player id
+    reserves: Vec<u32>,
+    /// indexed by player id
+    stats: Vec<PlayerStats>,
+    /// players with no territories left, in the order they lost them
+    eliminated: Vec<PlayerId>,
+    /// every action taken so far, which with the seed is enough to replay the game
+    #[serde(default)]
+    history: Vec<Action>,
+    rng: GameRng,
+}
+
+impl Game {
+    /// Start a game on `board`, with `players` taking their turns in the order given.
+    pub fn new(board: Board, players: Vec<PlayerId>, rng: GameRng) -> Game {
+        let seats = players.iter().map(|player| player.0 + 1).max().unwrap_or(0);
+        let stats = (0..seats)
+            .map(|id| PlayerStats {
+                largest_group: rules::largest_group(&board, PlayerId(id)),
+                ..PlayerStats::default()
+            })
+            .collect();
+        Game {
+            board,
+            players,
+            current: 0,
+            turn: 1,
+            reserves: vec![0; seats],
+            stats,
+            eliminated: vec![],
+            history: vec![],
+            rng,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn players(&self) -> &[PlayerId] {
+        &self.players
+    }
+
+    pub fn current_player(&self) -> PlayerId {
+        self.players[self.current]
+    }
+
+    /// The current round, counting from 1. It goes up each time play returns to the first seat.
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn reserve(&self, player: PlayerId) -> u32 {
+        self.reserves[player.0]
+    }
+
+    pub fn stats(&self, player: PlayerId) -> &PlayerStats {
+        &self.stats[player.0]
+    }
+
+    /// Players who have lost all their territories, in the order they lost them.
+    pub fn eliminated(&self) -> &[PlayerId] {
+        &self.eliminated
+    }
+
+    /// Players who still have territories, in turn order.
+    pub fn remaining(&self) -> Vec<PlayerId> {
+        self.players
+            .iter()
+            .cloned()
+            .filter(|&player| self.board.territories_of(player).next().is_some())
+            .collect()
+    }
+
+    /// Every action taken so far, in order.
+    pub fn history(&self) -> &[Action] {
+        &self.history
+    }
+
+    /// The last player standing, once everyone else is out.
+    pub fn winner(&self) -> Option<PlayerId> {
+        match self.remaining().as_slice() {
+            [winner] => Some(*winner),
+            _ => None,
+        }
+    }
+
+    /// Attack on behalf of the current player.
+    pub fn attack(&mut self, attack: Attack) -> Result<AttackOutcome, AttackError> {
+        let player = self.current_player();
+        let outcome = rules::attack(&mut self.board, player, attack, &mut self.rng)?;
+        self.history.push(Action::Attack(attack));
+
+        let stats = &mut self.stats[player.0];
+        stats.attacks += 1;
+        if outcome.captured() {
+            stats.captures += 1;
+            stats.largest_group = stats
+                .largest_group
+                .max(rules::largest_group(&self.board, player));
+
+            let defender = outcome.defender;
+            if self.board.territories_of(defender).next().is_none() {
+                self.stats[defender.0].eliminated = Some(self.turn);
+                self.eliminated.push(defender);
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Reinforce the current player and pass the turn to the next player still on the board.
+    pub fn end_turn(&mut self) -> Reinforcement {
+        let player = self.current_player();
+        let reinforcement =
+            rules::reinforce(&mut self.board, player, self.reserves[player.0], &mut self.rng);
+        self.reserves[player.0] = reinforcement.reserve;
+        self.history.push(Action::EndTurn);
+
+        for _ in 0..self.players.len() {
+            self.current = (self.current + 1) % self.players.len();
+            if self.current == 0 {
+                self.turn += 1;
+            }
+            if self.board.territories_of(self.current_player()).next().is_some() {
+                break;
+            }
+        }
+        reinforcement
+    }
+
+    pub fn apply(&mut self, action: Action) -> Result<ActionOutcome, AttackError> {
+        match action {
+            Action::Attack(attack) => self.attack(attack).map(ActionOutcome::Attack),
+            Action::EndTurn => Ok(ActionOutcome::EndTurn(self.end_turn())),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, FontAsset, Interactable, UiEvent, UiText, UiTransform},
+};
+
+use crate::assets::HexAssets;
+use crate::board::PlayerId;
+use crate::config::GameSettings;
+use crate::game::Game;
+use crate::gameplay::GameplayState;
+use crate::load_game::saves_dir;
+use crate::main_menu::MainMenuState;
+use crate::map_selection::MapSelectionState;
+use crate::mapgen;
+use crate::players::{load_palette, Players};
+use crate::save::{SavedGame, AUTOSAVE};
+use crate::seed::MapSeed;
+use crate::setup::GameSetup;
+
+use std::collections::HashMap;
+use std::fs;
+
+/// Shown once somebody has won: who placed where, how everyone played, and what to do next.
+pub struct GameOverState {
+    buttons: HashMap<Entity, GameOverAction>,
+    my_ui: Vec<Entity>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum GameOverAction {
+    /// play the same map again, with the same seats
+    Rematch,
+    /// pick another map for the same seats
+    NewMap,
+    MainMenu,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (game_over).";
+impl SimpleState for GameOverState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+        let game = (*world.read_resource::<Game>()).clone();
+        let players = (*world.read_resource::<Players>()).clone();
+        forget_autosave(world);
+
+        let winners = players.winners(&game.remaining()).unwrap_or_default();
+        let names: Vec<String> = winners.iter().map(|&p| players.name(p)).collect();
+        let title = match names.len() {
+            0 => "Game Over".to_string(),
+            1 => format!("{} wins!", names[0]),
+            _ => format!("{} win!", names.join(" and ")),
+        };
+        let title = create_title_text(world, &assets.font, &title);
+        let rounds = format!("after {} rounds", game.turn());
+        let rounds = create_text(world, &assets.font, "rounds", &rounds, -180.0, 24.0);
+        self.my_ui.extend(&[title, rounds]);
+
+        for (i, (place, player)) in placements(&game, &winners).into_iter().enumerate() {
+            let line = describe(&game, &players, place, player);
+            let id = format!("placement {}", i);
+            let y = -240.0 - i as f32 * 32.0;
+            let text = create_text(world, &assets.font, &id, &line, y, 18.0);
+            self.my_ui.push(text);
+        }
+
+        let buttons = [
+            ("rematch", GameOverAction::Rematch, -250.0),
+            ("new map", GameOverAction::NewMap, 0.0),
+            ("main menu", GameOverAction::MainMenu, 250.0),
+        ];
+        for (text, action, x) in buttons.iter() {
+            let button = create_button(world, &assets.font, text, *x, 30.0);
+            self.buttons.insert(button, *action);
+            self.my_ui.push(button);
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        self.my_ui.clear();
+        self.buttons.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        mut data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return self.perform(&mut data.world, GameOverAction::MainMenu);
+            }
+            if is_key_down(&event, VirtualKeyCode::Return) {
+                return self.perform(&mut data.world, GameOverAction::Rematch);
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click => {
+                    if let Some(action) = self.buttons.get(target).cloned() {
+                        return self.perform(&mut data.world, action);
+                    }
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+}
+
+impl GameOverState {
+    pub fn new() -> Self {
+        GameOverState {
+            buttons: HashMap::new(),
+            my_ui: vec![],
+        }
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(GameOverState::new())
+    }
+
+    fn perform(&mut self, world: &mut World, action: GameOverAction) -> SimpleTrans {
+        match action {
+            GameOverAction::Rematch => {
+                rematch(world);
+                Trans::Switch(GameplayState::new_boxed())
+            }
+            GameOverAction::NewMap => Trans::Switch(MapSelectionState::new_boxed()),
+            GameOverAction::MainMenu => Trans::Switch(MainMenuState::new_boxed()),
+        }
+    }
+}
+
+/// Remove the autosave if it is of this game, so "continue" doesn't lead back to a game that is
+/// already over.
+fn forget_autosave(world: &World) {
+    let path = saves_dir().join(AUTOSAVE);
+    let seed = *world.read_resource::<MapSeed>();
+    if let Ok(saved) = SavedGame::load(&path) {
+        if saved.seed == seed {
+            if let Err(error) = fs::remove_file(&path) {
+                println!("failed to remove {}: {}", path.display(), error);
+            }
+        }
+    }
+}
+
+/// Set up the same map and seats as the game that just ended, ready to play again.
+fn rematch(world: &mut World) {
+    let seed = *world.read_resource::<MapSeed>();
+    let setup = world.read_resource::<GameSetup>().clone();
+    // the map comes from the settings the last game was played with, whatever config.ron says now
+    let settings = world.read_resource::<GameSettings>().clone();
+    let board = mapgen::generate(&settings, &mut seed.rng()).board;
+
+    let players = Players::from_setup(&setup, &load_palette(world));
+    world.insert(players);
+    world.insert(Game::new(board, setup.turn_order(), seed.game_rng()));
+}
+
+/// Everyone in finishing order, with the place they finished in. The winners share first place,
+/// and the rest place in the reverse of the order they were knocked out.
+fn placements(game: &Game, winners: &[PlayerId]) -> Vec<(usize, PlayerId)> {
+    let mut placements: Vec<(usize, PlayerId)> = winners.iter().map(|&p| (1, p)).collect();
+    let remaining = game
+        .remaining()
+        .into_iter()
+        .filter(|player| !winners.contains(player));
+    let out = game.eliminated().iter().rev().cloned();
+    for player in remaining.chain(out) {
+        placements.push((placements.len() + 1, player));
+    }
+    placements
+}
+
+fn describe(game: &Game, players: &Players, place: usize, player: PlayerId) -> String {
+    let stats = game.stats(player);
+    let survived = stats.eliminated.unwrap_or_else(|| game.turn());
+    format!(
+        "{}. {}: {} attacks, {:.0}% won, largest group {}, survived {} rounds",
+        place,
+        players.name(player),
+        stats.attacks,
+        stats.success_rate() * 100.0,
+        stats.largest_group,
+        survived
+    )
+}
+
+fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        -100.0,
+        1.0,
+        800.0,
+        75.0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            64.0,
+        ))
+        .build();
+
+    text
+}
+
+fn create_text(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    id: &str,
+    text: &str,
+    y: f32,
+    size: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        id.to_string(),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        0.0,
+        y,
+        1.0,
+        800.0,
+        30.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            size,
+        ))
+        .build()
+}
+
+fn create_button(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    x: f32,
+    y: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::BottomMiddle,
+        Anchor::Middle,
+        x,
+        y,
+        1.0,
+        200.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
@@ -0,0 +1,243 @@
+//! Plays out an attack that has already been resolved: both sides' dice tumble, land on what was
+//! actually rolled, the totals are shown side by side, and the territory that changed flashes.
+//!
+//! The rules engine has already applied the attack by the time this starts; this only decides
+//! when the board view catches up with it.
+
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    prelude::*,
+    renderer::{palette::Srgb, SpriteRender, SpriteSheet},
+    ui::{Anchor, FontAsset, UiImage, UiText, UiTransform},
+};
+use rand::Rng;
+
+use crate::assets::HexAssets;
+use crate::board::{Board, TerritoryId};
+use crate::board_view::{player_color, BoardView};
+use crate::rules::{AttackOutcome, DIE_FACES};
+
+/// Seconds each stage lasts at normal speed.
+const ROLL_TIME: f32 = 1.0;
+const TOTALS_TIME: f32 = 0.8;
+const FLASH_TIME: f32 = 0.9;
+/// Seconds between tumbling dice changing faces.
+const TUMBLE_TIME: f32 = 0.08;
+/// How many times the changed territory blinks.
+const FLASHES: u32 = 3;
+
+const DIE_SIZE: f32 = 40.0;
+const DIE_GAP: f32 = 4.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Stage {
+    /// the dice change faces at random
+    Rolling,
+    /// the dice show what was rolled, with the totals below them
+    Totals,
+    /// the board is caught up, and the territory that changed blinks
+    Flash,
+    Done,
+}
+
+pub struct AttackAnimation {
+    outcome: AttackOutcome,
+    stage: Stage,
+    /// seconds into the current stage, already scaled by `speed`
+    elapsed: f32,
+    since_tumble: f32,
+    speed: f32,
+    attacker_dice: Vec<Entity>,
+    defender_dice: Vec<Entity>,
+    totals: Vec<Entity>,
+    font: Handle<FontAsset>,
+    /// whether the flashing territory is currently lit
+    lit: bool,
+}
+
+impl AttackAnimation {
+    /// Start animating `outcome`. A `speed` of 2.0 plays twice as fast as normal.
+    pub fn spawn(world: &mut World, outcome: AttackOutcome, speed: f32) -> AttackAnimation {
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+        let attacker_dice = create_dice(world, &assets.spritesheet, outcome.attacker_rolls.len(), -1.0);
+        let defender_dice = create_dice(world, &assets.spritesheet, outcome.defender_rolls.len(), 1.0);
+        AttackAnimation {
+            outcome,
+            stage: Stage::Rolling,
+            elapsed: 0.0,
+            since_tumble: 0.0,
+            speed: speed.max(0.1),
+            attacker_dice,
+            defender_dice,
+            totals: vec![],
+            font: assets.font,
+            lit: false,
+        }
+    }
+
+    pub fn outcome(&self) -> &AttackOutcome {
+        &self.outcome
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stage == Stage::Done
+    }
+
+    /// Advance the animation by `delta` seconds. `board` is the board after the attack, which
+    /// `view` is brought up to date with once the dice have landed.
+    pub fn update(&mut self, world: &mut World, view: &mut BoardView, board: &Board, delta: f32) {
+        self.elapsed += delta * self.speed;
+        match self.stage {
+            Stage::Rolling => {
+                self.since_tumble += delta * self.speed;
+                if self.since_tumble >= TUMBLE_TIME {
+                    self.since_tumble = 0.0;
+                    self.tumble(world);
+                }
+                if self.elapsed >= ROLL_TIME {
+                    self.settle(world);
+                    self.next_stage(Stage::Totals);
+                }
+            }
+            Stage::Totals => {
+                if self.elapsed >= TOTALS_TIME {
+                    self.next_stage(Stage::Flash);
+                }
+            }
+            Stage::Flash => {
+                let blink = FLASH_TIME / (FLASHES * 2) as f32;
+                let lit = (self.elapsed / blink) as u32 % 2 == 0;
+                if self.elapsed >= FLASH_TIME {
+                    view.set_highlight(world, board, None);
+                    self.next_stage(Stage::Done);
+                } else if lit != self.lit {
+                    self.lit = lit;
+                    let territory = if lit { Some(self.flashing()) } else { None };
+                    view.set_highlight(world, board, territory);
+                }
+            }
+            Stage::Done => {}
+        }
+    }
+
+    /// Remove everything the animation put on screen.
+    pub fn despawn(self, world: &mut World) {
+        let mut entities = self.attacker_dice;
+        entities.extend(self.defender_dice);
+        entities.extend(self.totals);
+        world
+            .delete_entities(&entities)
+            .expect("attack animation entity was deleted twice");
+    }
+
+    fn next_stage(&mut self, stage: Stage) {
+        self.stage = stage;
+        self.elapsed = 0.0;
+    }
+
+    /// The territory that changed: the one captured, or the one that lost its dice.
+    fn flashing(&self) -> TerritoryId {
+        if self.outcome.captured() {
+            self.outcome.attack.to
+        } else {
+            self.outcome.attack.from
+        }
+    }
+
+    /// Show random faces. This is only for show, so it doesn't touch the game's random numbers.
+    fn tumble(&self, world: &mut World) {
+        let mut rng = rand::thread_rng();
+        let mut images = world.write_storage::<UiImage>();
+        for entity in self.attacker_dice.iter().chain(self.defender_dice.iter()) {
+            if let Some(UiImage::Sprite(sprite)) = images.get_mut(*entity) {
+                sprite.sprite_number = rng.gen_range(0, DIE_FACES as usize);
+            }
+        }
+    }
+
+    /// Land the dice on the actual rolls and show both totals.
+    fn settle(&mut self, world: &mut World) {
+        {
+            let mut images = world.write_storage::<UiImage>();
+            let rolls = self
+                .outcome
+                .attacker_rolls
+                .iter()
+                .chain(self.outcome.defender_rolls.iter());
+            let dice = self.attacker_dice.iter().chain(self.defender_dice.iter());
+            for (entity, &face) in dice.zip(rolls) {
+                if let Some(UiImage::Sprite(sprite)) = images.get_mut(*entity) {
+                    sprite.sprite_number = face as usize - 1;
+                }
+            }
+        }
+
+        let attacker = player_color(world, self.outcome.attacker);
+        let defender = player_color(world, self.outcome.defender);
+        let attacker_total = self.outcome.attacker_total().to_string();
+        let defender_total = self.outcome.defender_total().to_string();
+        self.totals = vec![
+            create_total(world, &self.font, &attacker_total, -1.0, attacker),
+            create_total(world, &self.font, "vs", 0.0, Srgb::new(0.1, 0.1, 0.1)),
+            create_total(world, &self.font, &defender_total, 1.0, defender),
+        ];
+    }
+}
+
+/// A row of `count` dice at the top of the screen, growing out from the middle towards `side`
+/// (-1.0 for left, 1.0 for right).
+fn create_dice(world: &mut World, spritesheet: &Handle<SpriteSheet>, count: usize, side: f32) -> Vec<Entity> {
+    (0..count)
+        .map(|i| {
+            let x = side * (DIE_SIZE + i as f32 * (DIE_SIZE + DIE_GAP));
+            let transform = UiTransform::new(
+                "attack die".to_string(),
+                Anchor::TopMiddle,
+                Anchor::Middle,
+                x,
+                -170.0,
+                2.0,
+                DIE_SIZE,
+                DIE_SIZE,
+            );
+            world
+                .create_entity()
+                .with(transform)
+                .with(UiImage::Sprite(SpriteRender {
+                    sprite_sheet: spritesheet.clone(),
+                    sprite_number: i % DIE_FACES as usize,
+                }))
+                .build()
+        })
+        .collect()
+}
+
+fn create_total(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    side: f32,
+    color: Srgb,
+) -> Entity {
+    let transform = UiTransform::new(
+        "attack total".to_string(),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        side * 120.0,
+        -220.0,
+        2.0,
+        120.0,
+        40.0,
+    );
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [color.red, color.green, color.blue, 1.0],
+            32.0,
+        ))
+        .build()
+}
@@ -0,0 +1,4 @@
+mod animation;
+mod state;
+
+pub use state::{save_game, GameplayState};
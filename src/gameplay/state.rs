@@ -0,0 +1,897 @@
+use amethyst::{
+    assets::Handle,
+    core::{
+        ecs::Entity,
+        shrev::{EventChannel, ReaderId},
+        timing::Time,
+    },
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, FontAsset, Interactable, UiEvent, UiText, UiTransform},
+};
+
+use super::animation::AttackAnimation;
+use crate::ai::AiControl;
+use crate::assets::HexAssets;
+use crate::board::{PlayerId, TerritoryId};
+use crate::board_view::BoardView;
+use crate::config::{load_settings, GameSettings};
+use crate::entities::focus_camera;
+use crate::game::{Action, Game};
+use crate::game_over::GameOverState;
+use crate::handoff::HandoffState;
+use crate::hex::{Hex, Layout};
+use crate::join::JoinState;
+use crate::load_game::{replays_dir, saves_dir};
+use crate::main_menu::MainMenuState;
+use crate::net::{self, ClientMessage, HostEvent, HostMessage, NetError, Network, PeerId};
+use crate::picking::{HexEvent, HexPicking};
+use crate::rules::{self, Attack, AttackError, AttackOutcome};
+use crate::players::{Controller, Players};
+use crate::probability::win_probability;
+use crate::replay::Replay;
+use crate::save::{SavedGame, AUTOSAVE};
+use crate::seed::MapSeed;
+use crate::setup::{Difficulty, GameSetup};
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where the current player is in their turn.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Phase {
+    /// waiting for the player to pick one of their territories to attack from
+    SelectSource,
+    /// waiting for the player to pick a neighbor to attack
+    SelectTarget(TerritoryId),
+    /// an attack was chosen, and is resolved on the next update
+    ResolveAttack(Attack),
+    /// an attack was resolved, and is playing out on screen
+    ShowAttack,
+    /// the player is done; they are reinforced and play passes on at the next update
+    EndTurn,
+    /// the player's move was sent to the host, which hasn't played it yet
+    AwaitHost,
+}
+
+/// Where to join again after losing the host, and under what name, to get the same player back.
+struct Rejoin {
+    address: String,
+    name: String,
+    /// what went wrong, shown on the join screen
+    status: String,
+}
+
+/// Plays out a `Game` resource, one click at a time.
+pub struct GameplayState {
+    phase: Phase,
+    view: Option<BoardView>,
+    animation: Option<AttackAnimation>,
+    /// how fast attacks play out, or `None` to skip showing them
+    animation_speed: Option<f32>,
+    prompt: Option<Entity>,
+    feedback: Option<Entity>,
+    hover: Option<Entity>,
+    end_turn_button: Option<Entity>,
+    my_ui: Vec<Entity>,
+    hex_events: Option<ReaderId<HexEvent>>,
+    /// moves sent by the AI system
+    ai_actions: Option<ReaderId<Action>>,
+    /// whether to hand the screen over to the player whose turn it is before they can play
+    handoff: bool,
+    /// how many of the game's actions have been sent to clients, when hosting
+    sent: usize,
+    /// the player each client plays, when hosting
+    remote: HashMap<PeerId, PlayerId>,
+    /// players whose clients dropped out, when hosting; an AI plays for them until they are back
+    absent: Vec<PlayerId>,
+    /// what clients asked to do, waiting for the host to finish showing the last move, when hosting
+    intents: VecDeque<(PeerId, Action)>,
+    /// actions from the host waiting to be played, when joined to one
+    incoming: VecDeque<Action>,
+    /// set once the host is lost, when joined to one
+    rejoin: Option<Rejoin>,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (gameplay).";
+impl SimpleState for GameplayState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+        let board = world.read_resource::<Game>().board().clone();
+        let settings = load_settings(world);
+        self.animation_speed = if settings.skip_animations.unwrap_or(false) {
+            None
+        } else {
+            Some(settings.animation_speed.unwrap_or(1.0))
+        };
+        let seed = world.try_fetch::<MapSeed>().map_or(0, |seed| seed.0);
+        {
+            let mut control = world.write_resource::<AiControl>();
+            control.aggressiveness = settings.ai_aggressiveness;
+            control.seed = seed;
+            if let Some(iterations) = settings.ai_iterations {
+                control.budget.iterations = iterations;
+            }
+            control.budget.time = settings.ai_think_time.map(Duration::from_secs_f32);
+        }
+
+        // the lobby decided who plays which player
+        if let Some(network) = world.try_fetch::<Network>() {
+            if let Network::Host { seated, .. } = &*network {
+                self.remote = seated.clone();
+            }
+        }
+        self.sent = world.read_resource::<Game>().history().len();
+
+        focus_camera(world, &board);
+        self.view = Some(BoardView::spawn(world, &board));
+
+        let prompt = create_text(world, &assets.font, "prompt", -20.0, 28.0);
+        let feedback = create_text(world, &assets.font, "feedback", -60.0, 20.0);
+        let hover = create_text(world, &assets.font, "hover", -100.0, 20.0);
+        let end_turn = create_button(world, &assets.font, "end turn");
+        self.my_ui.extend(&[prompt, feedback, hover, end_turn]);
+        self.prompt = Some(prompt);
+        self.feedback = Some(feedback);
+        self.hover = Some(hover);
+        self.end_turn_button = Some(end_turn);
+
+        world.write_resource::<HexPicking>().layout = Some(Layout::BOARD);
+        self.hex_events = Some(
+            world
+                .write_resource::<EventChannel<HexEvent>>()
+                .register_reader(),
+        );
+        self.ai_actions = Some(
+            world
+                .write_resource::<EventChannel<Action>>()
+                .register_reader(),
+        );
+
+        self.enter(world, Phase::SelectSource);
+        self.handoff = is_handoff(world);
+    }
+
+    fn on_pause(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        // the handoff screen covers the board, so it shouldn't be picked or moved about
+        data.world.write_resource::<HexPicking>().layout = None;
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        world.write_resource::<HexPicking>().layout = Some(Layout::BOARD);
+        // drop anything picked while the board was covered
+        if let Some(reader) = &mut self.hex_events {
+            let _ = world
+                .read_resource::<EventChannel<HexEvent>>()
+                .read(reader)
+                .count();
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<HexPicking>() = HexPicking::default();
+        self.hex_events = None;
+        self.ai_actions = None;
+        *data.world.write_resource::<AiControl>() = AiControl::default();
+        record_replay(data.world);
+        data.world.remove::<Network>();
+        if let Some(animation) = self.animation.take() {
+            animation.despawn(data.world);
+        }
+        if let Some(view) = self.view.take() {
+            view.despawn(data.world);
+        }
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        self.my_ui.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        mut data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                save_game(data.world, AUTOSAVE);
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                // with nothing left to cancel, leave for the main menu; "continue" comes back
+                if self.phase == Phase::SelectSource {
+                    save_game(data.world, AUTOSAVE);
+                    return Trans::Switch(MainMenuState::new_boxed());
+                }
+                self.cancel(&mut data.world);
+            }
+            if is_key_down(&event, VirtualKeyCode::F5) {
+                self.quick_save(&mut data.world);
+            }
+            if is_key_down(&event, VirtualKeyCode::Space)
+                || is_key_down(&event, VirtualKeyCode::Return)
+            {
+                self.end_turn(&mut data.world);
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click if Some(*target) == self.end_turn_button => {
+                    self.end_turn(&mut data.world);
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let world = &mut *data.world;
+
+        let events: Vec<HexEvent> = match &mut self.hex_events {
+            Some(reader) => world
+                .read_resource::<EventChannel<HexEvent>>()
+                .read(reader)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        for event in events {
+            match event {
+                HexEvent::Clicked(hex) => {
+                    if let Some(territory) = territory_at(world, hex) {
+                        self.select(world, territory);
+                    }
+                }
+                HexEvent::Hovered(hex) => {
+                    let territory = hex.and_then(|hex| territory_at(world, hex));
+                    self.hover(world, territory);
+                }
+            }
+        }
+
+        let actions: Vec<Action> = match &mut self.ai_actions {
+            Some(reader) => world
+                .read_resource::<EventChannel<Action>>()
+                .read(reader)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        for action in actions {
+            // a move thought up for a player who came back in the meantime is dropped
+            if self.phase != Phase::SelectSource || !is_ai_controlled(world) {
+                continue;
+            }
+            self.enter(world, phase_for(action));
+        }
+
+        self.poll_network(world);
+        if let Some(Rejoin {
+            address,
+            name,
+            status,
+        }) = self.rejoin.take()
+        {
+            return Trans::Switch(JoinState::rejoin_boxed(address, name, status));
+        }
+        if let Phase::SelectSource | Phase::AwaitHost = self.phase {
+            if let Some(action) = self.incoming.pop_front() {
+                self.enter(world, phase_for(action));
+            }
+        }
+        if self.phase == Phase::SelectSource {
+            if let Some((peer, action)) = self.intents.pop_front() {
+                self.take_intent(world, peer, action);
+            }
+        }
+
+        match self.phase {
+            Phase::ResolveAttack(attack) => {
+                let outcome = world.write_resource::<Game>().attack(attack);
+                match (outcome, self.animation_speed) {
+                    (Ok(outcome), Some(speed)) => {
+                        self.animation = Some(AttackAnimation::spawn(world, outcome, speed));
+                        self.phase = Phase::ShowAttack;
+                    }
+                    (Ok(outcome), None) => {
+                        self.report(world, &outcome);
+                        self.enter(world, Phase::SelectSource);
+                    }
+                    (Err(error), _) => {
+                        self.set_text(world, self.feedback, &error.to_string());
+                        self.enter(world, Phase::SelectSource);
+                    }
+                }
+            }
+            Phase::ShowAttack => {
+                let delta = world.read_resource::<Time>().delta_seconds();
+                let board = world.read_resource::<Game>().board().clone();
+                let done = match (&mut self.animation, &mut self.view) {
+                    (Some(animation), Some(view)) => {
+                        animation.update(world, view, &board, delta);
+                        animation.is_done()
+                    }
+                    _ => true,
+                };
+                if done {
+                    self.finish_animation(world);
+                }
+            }
+            Phase::EndTurn => {
+                let reinforcement = world.write_resource::<Game>().end_turn();
+                let message = format!(
+                    "{} received {} dice ({} in reserve)",
+                    player_name(world, reinforcement.player),
+                    reinforcement.placed.len(),
+                    reinforcement.reserve
+                );
+                self.set_text(world, self.feedback, &message);
+                self.enter(world, Phase::SelectSource);
+                self.handoff = is_handoff(world);
+            }
+            Phase::SelectSource | Phase::SelectTarget(_) | Phase::AwaitHost => {}
+        }
+        self.send_actions(world);
+
+        // wait for the last attack to finish playing out before leaving the board
+        if self.phase == Phase::SelectSource && is_game_over(world) {
+            return Trans::Switch(GameOverState::new_boxed());
+        }
+        if self.handoff {
+            self.handoff = false;
+            let current = world.read_resource::<Game>().current_player();
+            let players = world.read_resource::<Players>();
+            let screen = HandoffState::new_boxed(players.name(current), players.color(current));
+            return Trans::Push(screen);
+        }
+        Trans::None
+    }
+}
+
+impl GameplayState {
+    pub fn new() -> Self {
+        GameplayState {
+            phase: Phase::SelectSource,
+            view: None,
+            animation: None,
+            animation_speed: Some(1.0),
+            prompt: None,
+            feedback: None,
+            hover: None,
+            end_turn_button: None,
+            my_ui: vec![],
+            hex_events: None,
+            ai_actions: None,
+            handoff: false,
+            sent: 0,
+            remote: HashMap::new(),
+            absent: vec![],
+            intents: VecDeque::new(),
+            incoming: VecDeque::new(),
+            rejoin: None,
+        }
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(GameplayState::new())
+    }
+
+    /// Move to `phase`, updating the prompt and the board to match.
+    fn enter(&mut self, world: &mut World, phase: Phase) {
+        self.phase = phase;
+        let game = (*world.read_resource::<Game>()).clone();
+        let current = game.current_player();
+        let ai_turn = is_ai_turn(world);
+        let player = match world.read_resource::<Players>().get(current) {
+            Some(descriptor) if descriptor.controller != Controller::Human => {
+                format!("{} ({})", descriptor.name, descriptor.controller)
+            }
+            _ => player_name(world, current),
+        };
+        // the AI is asked for a move whenever it is back to choosing one
+        world.write_resource::<AiControl>().waiting = match phase {
+            Phase::SelectSource if ai_turn => Some(current),
+            _ => None,
+        };
+        let (prompt, highlight) = match phase {
+            Phase::SelectSource if ai_turn => (format!("{} is thinking...", player), None),
+            Phase::SelectSource => (
+                format!("{}: pick a territory to attack from", player),
+                None,
+            ),
+            Phase::SelectTarget(from) => (
+                format!("{}: pick a neighbor to attack", player),
+                Some(from),
+            ),
+            Phase::ResolveAttack(attack) => (format!("{} attacks!", player), Some(attack.from)),
+            Phase::ShowAttack => (format!("{} attacks!", player), None),
+            Phase::EndTurn => (format!("{} ends their turn", player), None),
+            Phase::AwaitHost => (format!("{}: waiting for the host...", player), None),
+        };
+        self.set_text(world, self.prompt, &prompt);
+        if let Some(view) = &mut self.view {
+            view.set_highlight(world, game.board(), highlight);
+        }
+    }
+
+    /// Handle a click on `territory`.
+    fn select(&mut self, world: &mut World, territory: TerritoryId) {
+        if is_ai_turn(world) {
+            return;
+        }
+        let (player, board) = {
+            let game = world.read_resource::<Game>();
+            (game.current_player(), game.board().clone())
+        };
+        let can_attack_from = |territory: TerritoryId| {
+            if board.owner(territory) != player {
+                Err(AttackError::NotYourTerritory(territory))
+            } else if board.dice(territory) < 2 {
+                Err(AttackError::NotEnoughDice(territory))
+            } else {
+                Ok(())
+            }
+        };
+
+        let next = match self.phase {
+            Phase::SelectSource => can_attack_from(territory).map(|_| Phase::SelectTarget(territory)),
+            Phase::SelectTarget(from) if from == territory => Ok(Phase::SelectSource),
+            Phase::SelectTarget(_) if board.owner(territory) == player => {
+                can_attack_from(territory).map(|_| Phase::SelectTarget(territory))
+            }
+            Phase::SelectTarget(from) => {
+                let attack = Attack {
+                    from,
+                    to: territory,
+                };
+                rules::check_attack(&board, player, attack).map(|_| Phase::ResolveAttack(attack))
+            }
+            Phase::ResolveAttack(_) | Phase::ShowAttack | Phase::EndTurn | Phase::AwaitHost => {
+                return
+            }
+        };
+        match next {
+            Ok(phase) => {
+                self.set_text(world, self.feedback, "");
+                self.choose(world, phase);
+            }
+            Err(error) => self.set_text(world, self.feedback, &error.to_string()),
+        }
+    }
+
+    /// Describe the territory under the mouse.
+    fn hover(&mut self, world: &mut World, territory: Option<TerritoryId>) {
+        let description = match territory {
+            Some(territory) => {
+                let game = world.read_resource::<Game>();
+                let board = game.board();
+                let mut description = format!(
+                    "{}'s territory, {} dice",
+                    player_name(world, board.owner(territory)),
+                    board.dice(territory)
+                );
+                // while picking a target, show the odds of taking it
+                if let Phase::SelectTarget(from) = self.phase {
+                    let attack = Attack {
+                        from,
+                        to: territory,
+                    };
+                    if rules::check_attack(board, game.current_player(), attack).is_ok() {
+                        let odds = win_probability(board.dice(from), board.dice(territory));
+                        description.push_str(&format!(", {:.0}% to win", odds * 100.0));
+                    }
+                }
+                description
+            }
+            None => String::new(),
+        };
+        self.set_text(world, self.hover, &description);
+    }
+
+    /// Step back out of the current selection.
+    fn cancel(&mut self, world: &mut World) {
+        match self.phase {
+            Phase::SelectTarget(_) => {
+                self.set_text(world, self.feedback, "");
+                self.enter(world, Phase::SelectSource);
+            }
+            Phase::SelectSource => self.set_text(world, self.feedback, "nothing to cancel"),
+            Phase::ShowAttack => self.finish_animation(world),
+            Phase::ResolveAttack(_) | Phase::EndTurn | Phase::AwaitHost => {}
+        }
+    }
+
+    fn end_turn(&mut self, world: &mut World) {
+        if is_ai_turn(world) {
+            return;
+        }
+        match self.phase {
+            Phase::SelectSource | Phase::SelectTarget(_) => self.choose(world, Phase::EndTurn),
+            Phase::ResolveAttack(_) | Phase::ShowAttack | Phase::EndTurn | Phase::AwaitHost => {}
+        }
+    }
+
+    /// Go on to `phase`, chosen by the player at this screen. When joined to a host, the host
+    /// decides what happens, so it is only asked.
+    fn choose(&mut self, world: &mut World, phase: Phase) {
+        let action = match phase {
+            Phase::ResolveAttack(attack) => Action::Attack(attack),
+            Phase::EndTurn => Action::EndTurn,
+            _ => {
+                self.enter(world, phase);
+                return;
+            }
+        };
+        let sent = match world.try_fetch::<Network>() {
+            Some(network) => match &*network {
+                Network::Client { client, .. } => Some(client.send(&ClientMessage::Intent(action))),
+                Network::Host { .. } => None,
+            },
+            None => None,
+        };
+        match sent {
+            Some(Ok(())) => self.enter(world, Phase::AwaitHost),
+            Some(Err(error)) => {
+                let message = format!("couldn't reach the host: {}", error);
+                self.set_text(world, self.feedback, &message);
+            }
+            None => self.enter(world, phase),
+        }
+    }
+
+    /// Take in whatever came over the network since the last update.
+    fn poll_network(&mut self, world: &mut World) {
+        let (events, messages, rejoin) = match world.try_fetch::<Network>() {
+            Some(network) => match &*network {
+                Network::Host { host, .. } => (host.poll(), vec![], None),
+                Network::Client { client, .. } => {
+                    let rejoin = (client.address().to_string(), client.name().to_string());
+                    (vec![], client.poll(), Some(rejoin))
+                }
+            },
+            None => return,
+        };
+        for event in events {
+            self.host_event(world, event);
+        }
+        for message in messages {
+            match message {
+                Ok(HostMessage::Resolved(action)) => self.incoming.push_back(action),
+                Ok(HostMessage::Rejected(reason)) => {
+                    self.set_text(world, self.feedback, &reason);
+                    if self.phase == Phase::AwaitHost {
+                        self.enter(world, Phase::SelectSource);
+                    }
+                }
+                Ok(HostMessage::Refused(reason)) => {
+                    let message = NetError::Refused(reason).to_string();
+                    self.set_text(world, self.feedback, &message);
+                }
+                Ok(HostMessage::Lobby { .. }) | Ok(HostMessage::Start { .. }) => {}
+                Err(error) => {
+                    // join again under the same name to get the same player back
+                    if let Some((address, name)) = rejoin.clone() {
+                        self.rejoin = Some(Rejoin {
+                            address,
+                            name,
+                            status: format!("lost the host: {}; join again to play on", error),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle something a client did, when hosting.
+    fn host_event(&mut self, world: &mut World, event: HostEvent) {
+        match event {
+            HostEvent::Joined(peer, name) => {
+                let players = world.read_resource::<Players>().clone();
+                let name = name.trim();
+                let found = self
+                    .absent
+                    .iter()
+                    .position(|&player| players.name(player).eq_ignore_ascii_case(name));
+                match found {
+                    Some(i) => {
+                        let player = self.absent.remove(i);
+                        self.seat_again(world, peer, player);
+                    }
+                    None => {
+                        let reason = "the game has already started";
+                        with_host(world, |host| host.refuse(peer, reason));
+                    }
+                }
+            }
+            HostEvent::Left(peer) => {
+                let player = match self.remote.remove(&peer) {
+                    Some(player) => player,
+                    None => return,
+                };
+                // an AI takes over, so everyone else can play on
+                if let Some(p) = world.write_resource::<Players>().0.get_mut(player.0) {
+                    p.controller = Controller::Ai(Difficulty::Normal);
+                }
+                self.absent.push(player);
+                let message = format!(
+                    "{} left; an AI plays for them until they are back",
+                    player_name(world, player)
+                );
+                self.set_text(world, self.feedback, &message);
+                if self.phase == Phase::SelectSource {
+                    self.enter(world, Phase::SelectSource);
+                }
+            }
+            // clients may be ahead of the host's animations, so their moves wait for it
+            HostEvent::Message(peer, ClientMessage::Intent(action)) => {
+                self.intents.push_back((peer, action));
+            }
+            // what clients say in the lobby doesn't matter once the game has started
+            HostEvent::Message(..) => {}
+        }
+    }
+
+    /// Give a client who dropped out their player back, and send them the game as it stands.
+    fn seat_again(&mut self, world: &mut World, peer: PeerId, player: PlayerId) {
+        if let Some(p) = world.write_resource::<Players>().0.get_mut(player.0) {
+            p.controller = Controller::Remote;
+        }
+        self.remote.insert(peer, player);
+        let replay = Box::new(replay_so_far(world));
+        let start = HostMessage::Start {
+            replay,
+            you: player,
+        };
+        with_host(world, |host| host.send(peer, &start));
+        let message = format!("{} is back", player_name(world, player));
+        self.set_text(world, self.feedback, &message);
+        if self.phase == Phase::SelectSource {
+            self.enter(world, Phase::SelectSource);
+        }
+    }
+
+    /// Play what a client asked to do, or tell them why not.
+    fn take_intent(&mut self, world: &mut World, peer: PeerId, action: Action) {
+        match self.check_intent(world, peer, action) {
+            Ok(phase) => self.enter(world, phase),
+            Err(reason) => {
+                let rejected = HostMessage::Rejected(reason);
+                with_host(world, |host| host.send(peer, &rejected));
+            }
+        }
+    }
+
+    /// Whether a client may do what they asked, in the game as it stands.
+    fn check_intent(&self, world: &World, peer: PeerId, action: Action) -> Result<Phase, String> {
+        let player = match self.remote.get(&peer) {
+            Some(&player) => player,
+            None => return Err("you aren't playing".to_string()),
+        };
+        net::check_intent(&world.read_resource::<Game>(), player, action)?;
+        Ok(match action {
+            Action::Attack(attack) => Phase::ResolveAttack(attack),
+            Action::EndTurn => Phase::EndTurn,
+        })
+    }
+
+    /// When hosting, send the clients every action played since the last update.
+    fn send_actions(&mut self, world: &World) {
+        let game = world.read_resource::<Game>();
+        let history = game.history();
+        if history.len() > self.sent {
+            let unsent = &history[self.sent..];
+            with_host(world, |host| {
+                for &action in unsent {
+                    host.broadcast(&HostMessage::Resolved(action));
+                }
+            });
+            self.sent = history.len();
+        }
+    }
+
+    /// Save to a file of its own, named for the map and round.
+    fn quick_save(&mut self, world: &mut World) {
+        let seed = *world.read_resource::<MapSeed>();
+        let name = format!("{}-round-{}.ron", seed, world.read_resource::<Game>().turn());
+        let message = match save_game(world, &name) {
+            Some(path) => format!("saved to {}", path.display()),
+            None => "the game could not be saved".to_string(),
+        };
+        self.set_text(world, self.feedback, &message);
+    }
+
+    /// Stop showing the attack, skipping whatever was left of it.
+    fn finish_animation(&mut self, world: &mut World) {
+        if let Some(animation) = self.animation.take() {
+            self.report(world, animation.outcome());
+            animation.despawn(world);
+        }
+        self.enter(world, Phase::SelectSource);
+    }
+
+    /// Describe how an attack went.
+    fn report(&self, world: &mut World, outcome: &AttackOutcome) {
+        let verdict = if outcome.captured() {
+            "captured!"
+        } else {
+            "repelled."
+        };
+        let mut message = format!(
+            "{} rolled {} against {}: {}",
+            player_name(world, outcome.attacker),
+            outcome.attacker_total(),
+            outcome.defender_total(),
+            verdict
+        );
+        let eliminated = world.read_resource::<Game>().stats(outcome.defender).eliminated;
+        if outcome.captured() && eliminated.is_some() {
+            message.push_str(&format!(" {} is out!", player_name(world, outcome.defender)));
+        }
+        self.set_text(world, self.feedback, &message);
+    }
+
+    fn set_text(&self, world: &mut World, entity: Option<Entity>, text: &str) {
+        if let Some(entity) = entity {
+            let mut texts = world.write_storage::<UiText>();
+            texts.get_mut(entity).expect(EXPECTED).text = text.to_string();
+        }
+    }
+}
+
+/// The phase that plays `action`.
+fn phase_for(action: Action) -> Phase {
+    match action {
+        Action::Attack(attack) => Phase::ResolveAttack(attack),
+        Action::EndTurn => Phase::EndTurn,
+    }
+}
+
+/// Do something with the connections to clients, when hosting.
+fn with_host(world: &World, f: impl FnOnce(&net::Host)) {
+    if let Some(network) = world.try_fetch::<Network>() {
+        if let Network::Host { host, .. } = &*network {
+            f(host);
+        }
+    }
+}
+
+fn player_name(world: &World, player: PlayerId) -> String {
+    world.read_resource::<Players>().name(player)
+}
+
+/// Whether the player whose turn it is plays on their own.
+fn is_ai_turn(world: &World) -> bool {
+    let current = world.read_resource::<Game>().current_player();
+    match world.read_resource::<Players>().get(current) {
+        Some(descriptor) => descriptor.controller != Controller::Human,
+        None => false,
+    }
+}
+
+/// Whether an AI plays for the player whose turn it is.
+fn is_ai_controlled(world: &World) -> bool {
+    let current = world.read_resource::<Game>().current_player();
+    match world.read_resource::<Players>().get(current).map(|p| p.controller) {
+        Some(Controller::Ai(_)) => true,
+        _ => false,
+    }
+}
+
+/// Whether a person is about to play, with another person still in the game at the same screen.
+fn is_handoff(world: &World) -> bool {
+    let remaining = world.read_resource::<Game>().remaining();
+    !is_ai_turn(world) && world.read_resource::<Players>().is_hot_seat(&remaining)
+}
+
+/// Save the game as it stands under `name` in the saves folder, returning where it went.
+pub fn save_game(world: &World, name: &str) -> Option<PathBuf> {
+    // a network game can't be picked up again without everyone in it
+    if world.try_fetch::<Network>().is_some() {
+        return None;
+    }
+    let saved = SavedGame::new(
+        *world.read_resource::<MapSeed>(),
+        world.read_resource::<GameSettings>().clone(),
+        world.read_resource::<GameSetup>().clone(),
+        world.read_resource::<Players>().clone(),
+        world.read_resource::<Game>().clone(),
+    );
+    let path = saves_dir().join(name);
+    match saved.save(&path) {
+        Ok(()) => Some(path),
+        Err(error) => {
+            println!("failed to save the game to {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+/// Everything played so far, with what it was played on.
+fn replay_so_far(world: &World) -> Replay {
+    let setup = world.read_resource::<GameSetup>().clone();
+    let settings = world.read_resource::<GameSettings>().clone();
+    let seed = *world.read_resource::<MapSeed>();
+    let players = world.read_resource::<Players>().clone();
+    Replay::record(seed, settings, setup, players, &world.read_resource::<Game>())
+}
+
+/// Write everything played so far to the replays folder, named for the map and the time.
+fn record_replay(world: &World) {
+    if world.read_resource::<Game>().history().is_empty() {
+        return;
+    }
+    let replay = replay_so_far(world);
+    let seed = replay.seed;
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = replays_dir().join(format!("{}-{}.ron", seed, time));
+    if let Err(error) = replay.save(&path) {
+        println!("failed to record a replay to {}: {}", path.display(), error);
+    }
+}
+
+/// Whether somebody has won: one player, or one team, owns every territory.
+fn is_game_over(world: &World) -> bool {
+    let remaining = world.read_resource::<Game>().remaining();
+    world.read_resource::<Players>().winners(&remaining).is_some()
+}
+
+fn territory_at(world: &World, hex: Hex) -> Option<TerritoryId> {
+    world.read_resource::<Game>().board().territory_at(hex)
+}
+
+fn create_text(world: &mut World, font: &Handle<FontAsset>, id: &str, y: f32, size: f32) -> Entity {
+    let transform = UiTransform::new(
+        id.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        y,
+        1.0,
+        800.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            size,
+        ))
+        .build()
+}
+
+fn create_button(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::BottomRight,
+        Anchor::BottomRight,
+        -20.0,
+        20.0,
+        1.0,
+        200.0,
+        50.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
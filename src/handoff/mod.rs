@@ -0,0 +1,147 @@
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, FontAsset, Interactable, Stretch, UiEvent, UiImage, UiText, UiTransform},
+};
+
+use crate::assets::HexAssets;
+use crate::gameplay;
+use crate::save::AUTOSAVE;
+
+const BACKGROUND: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+/// Shown over the board between the turns of players sharing the screen, so the next one knows to
+/// take the mouse. Pushed by the gameplay state, and popped by a click or a key.
+pub struct HandoffState {
+    name: String,
+    color: (f32, f32, f32),
+    my_ui: Vec<Entity>,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (handoff).";
+impl SimpleState for HandoffState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        let background = create_background(world);
+        let (r, g, b) = self.color;
+        let title = format!("{}'s turn", self.name);
+        let title = create_text(world, &assets.font, &title, 40.0, 64.0, [r, g, b, 1.0]);
+        let prompt = "click to continue";
+        let prompt = create_text(
+            world,
+            &assets.font,
+            prompt,
+            -40.0,
+            24.0,
+            [0.1, 0.1, 0.1, 1.0],
+        );
+        self.my_ui.extend(&[background, title, prompt]);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        self.my_ui.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                // the game underneath is kept, as it is when closing during a turn
+                gameplay::save_game(data.world, AUTOSAVE);
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Return)
+                || is_key_down(&event, VirtualKeyCode::Space)
+            {
+                return Trans::Pop;
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click if self.my_ui.contains(target) => {
+                    return Trans::Pop;
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+}
+
+impl HandoffState {
+    pub fn new(name: String, color: (f32, f32, f32)) -> Self {
+        HandoffState {
+            name,
+            color,
+            my_ui: vec![],
+        }
+    }
+    pub fn new_boxed(name: String, color: (f32, f32, f32)) -> Box<Self> {
+        Box::new(HandoffState::new(name, color))
+    }
+}
+
+/// Covers the whole window, hiding the board and catching clicks meant for it.
+fn create_background(world: &mut World) -> Entity {
+    let transform = UiTransform::new(
+        "handoff".to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        0.0,
+        0.0,
+        5.0,
+        0.0,
+        0.0,
+    )
+    .with_stretch(Stretch::XY {
+        x_margin: 0.0,
+        y_margin: 0.0,
+        keep_aspect_ratio: false,
+    });
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiImage::SolidColor(BACKGROUND))
+        .build()
+}
+
+fn create_text(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    y: f32,
+    size: f32,
+    color: [f32; 4],
+) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        0.0,
+        y,
+        6.0,
+        800.0,
+        80.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(font.clone(), text.to_string(), color, size))
+        .build()
+}
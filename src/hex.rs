@@ -0,0 +1,402 @@
+//! Hex grid coordinates.
+//!
+//! Hexes are flat-topped and laid out in columns, with even columns sitting half a hex higher
+//! than odd ones. The board stores hexes by `Offset` (column, row); everything else works in
+//! axial coordinates (`Hex`), where neighbors, distances and lines are simple arithmetic.
+//! See https://www.redblobgames.com/grids/hexagons/ for the background.
+
+use serde::{Deserialize, Serialize};
+
+use std::ops::{Add, Mul, Sub};
+
+/// A hex in axial coordinates. The implied third cube coordinate is `s = -q - r`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// The six directions, counter-clockwise starting from the upper right.
+/// Edge `i` of a hex, between corners `i` and `i + 1`, faces direction `i`.
+pub const DIRECTIONS: [Hex; 6] = [
+    Hex { q: 1, r: 0 },
+    Hex { q: 0, r: 1 },
+    Hex { q: -1, r: 1 },
+    Hex { q: -1, r: 0 },
+    Hex { q: 0, r: -1 },
+    Hex { q: 1, r: -1 },
+];
+
+impl Hex {
+    pub const ORIGIN: Hex = Hex { q: 0, r: 0 };
+
+    pub fn new(q: i32, r: i32) -> Hex {
+        Hex { q, r }
+    }
+
+    pub fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    /// The adjacent hex in one of the six `DIRECTIONS`.
+    pub fn neighbor(self, direction: usize) -> Hex {
+        self + DIRECTIONS[direction % 6]
+    }
+
+    pub fn neighbors(self) -> [Hex; 6] {
+        let mut neighbors = [self; 6];
+        for (direction, neighbor) in neighbors.iter_mut().enumerate() {
+            *neighbor = self.neighbor(direction);
+        }
+        neighbors
+    }
+
+    /// The number of steps between two hexes.
+    pub fn distance(self, other: Hex) -> u32 {
+        let d = self - other;
+        ((d.q.abs() + d.r.abs() + d.s().abs()) / 2) as u32
+    }
+
+    /// Every hex on a straight line from `self` to `other`, including both ends.
+    pub fn line_to(self, other: Hex) -> Line {
+        Line {
+            from: self,
+            to: other,
+            steps: self.distance(other),
+            step: 0,
+        }
+    }
+
+    /// The hexes exactly `radius` steps away, counter-clockwise.
+    pub fn ring(self, radius: u32) -> Ring {
+        Ring::new(self, radius)
+    }
+
+    /// `self` followed by each ring out to `radius`, innermost first.
+    pub fn spiral(self, radius: u32) -> Spiral {
+        Spiral {
+            center: self,
+            radius,
+            current: None,
+        }
+    }
+
+    /// Round fractional cube coordinates to the hex containing them.
+    fn round(q: f32, r: f32) -> Hex {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Hex::new(rq as i32, rr as i32)
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+    fn mul(self, k: i32) -> Hex {
+        Hex::new(self.q * k, self.r * k)
+    }
+}
+
+/// A hex addressed by column and row, as the board stores them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Offset {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl Offset {
+    pub fn new(col: i32, row: i32) -> Offset {
+        Offset { col, row }
+    }
+}
+
+impl From<Offset> for Hex {
+    fn from(offset: Offset) -> Hex {
+        let Offset { col, row } = offset;
+        Hex::new(col, row - (col + (col & 1)) / 2)
+    }
+}
+
+impl From<Hex> for Offset {
+    fn from(hex: Hex) -> Offset {
+        let Hex { q, r } = hex;
+        Offset::new(q, r + (q + (q & 1)) / 2)
+    }
+}
+
+/// Steps along a straight line between two hexes.
+pub struct Line {
+    from: Hex,
+    to: Hex,
+    steps: u32,
+    step: u32,
+}
+
+impl Iterator for Line {
+    type Item = Hex;
+    fn next(&mut self) -> Option<Hex> {
+        if self.step > self.steps {
+            return None;
+        }
+        let t = if self.steps == 0 {
+            0.0
+        } else {
+            self.step as f32 / self.steps as f32
+        };
+        self.step += 1;
+        // nudge off the exact edges between hexes so ties round consistently
+        let lerp = |a: i32, b: i32, nudge: f32| a as f32 + nudge + (b - a) as f32 * t;
+        Some(Hex::round(
+            lerp(self.from.q, self.to.q, 1e-6),
+            lerp(self.from.r, self.to.r, 1e-6),
+        ))
+    }
+}
+
+/// Walks the hexes at a fixed distance from a center.
+pub struct Ring {
+    current: Hex,
+    radius: u32,
+    remaining: u32,
+}
+
+impl Ring {
+    fn new(center: Hex, radius: u32) -> Ring {
+        Ring {
+            current: center + DIRECTIONS[4] * radius as i32,
+            radius,
+            // a ring of radius zero is just the center
+            remaining: if radius == 0 { 1 } else { 6 * radius },
+        }
+    }
+}
+
+impl Iterator for Ring {
+    type Item = Hex;
+    fn next(&mut self) -> Option<Hex> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let hex = self.current;
+        if self.radius > 0 {
+            let side = (6 * self.radius - self.remaining) / self.radius;
+            self.current = self.current.neighbor(side as usize);
+        }
+        self.remaining -= 1;
+        Some(hex)
+    }
+}
+
+/// Walks a filled hexagon outward from its center, one ring at a time.
+pub struct Spiral {
+    center: Hex,
+    radius: u32,
+    current: Option<(u32, Ring)>,
+}
+
+impl Iterator for Spiral {
+    type Item = Hex;
+    fn next(&mut self) -> Option<Hex> {
+        loop {
+            match &mut self.current {
+                None => {
+                    self.current = Some((0, Ring::new(self.center, 0)));
+                }
+                Some((radius, ring)) => {
+                    if let Some(hex) = ring.next() {
+                        return Some(hex);
+                    }
+                    if *radius >= self.radius {
+                        return None;
+                    }
+                    let next = *radius + 1;
+                    self.current = Some((next, Ring::new(self.center, next)));
+                }
+            }
+        }
+    }
+}
+
+/// Maps hexes to world positions and back.
+///
+/// `spacing` is the distance between neighboring columns and between hexes in the same column.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub origin: (f32, f32),
+    pub spacing: (f32, f32),
+}
+
+impl Layout {
+    /// The layout of the game board; it matches the hexes in `hexes/hexes.png`.
+    pub const BOARD: Layout = Layout {
+        origin: (0.0, 28.0),
+        spacing: (49.0, 56.0),
+    };
+
+    /// The center of `hex`.
+    pub fn hex_to_pixel(&self, hex: Hex) -> (f32, f32) {
+        let q = hex.q as f32;
+        let r = hex.r as f32;
+        (
+            self.origin.0 + self.spacing.0 * q,
+            self.origin.1 + self.spacing.1 * (r + q / 2.0),
+        )
+    }
+
+    /// The hex containing the point (x, y).
+    pub fn pixel_to_hex(&self, x: f32, y: f32) -> Hex {
+        let q = (x - self.origin.0) / self.spacing.0;
+        let r = (y - self.origin.1) / self.spacing.1 - q / 2.0;
+        Hex::round(q, r)
+    }
+
+    /// The six corners of `hex`, counter-clockwise starting from the right.
+    pub fn corners(&self, hex: Hex) -> [(f32, f32); 6] {
+        let (cx, cy) = self.hex_to_pixel(hex);
+        let w = self.spacing.0 / 1.5;
+        let h = self.spacing.1 / 2.0;
+        [
+            (cx + w, cy),
+            (cx + w / 2.0, cy + h),
+            (cx - w / 2.0, cy + h),
+            (cx - w, cy),
+            (cx - w / 2.0, cy - h),
+            (cx + w / 2.0, cy - h),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    /// A spread of hexes around the origin, in both column parities and every quadrant.
+    fn sample() -> Vec<Hex> {
+        Hex::ORIGIN.spiral(5).collect()
+    }
+
+    #[test]
+    fn offsets_round_trip() {
+        for col in -6..6 {
+            for row in -6..6 {
+                let offset = Offset::new(col, row);
+                assert_eq!(Offset::from(Hex::from(offset)), offset);
+            }
+        }
+        for hex in sample() {
+            assert_eq!(Hex::from(Offset::from(hex)), hex);
+        }
+    }
+
+    #[test]
+    fn even_columns_sit_higher() {
+        // a hex in an even column borders its own row and the next one in the columns beside it
+        let hex: Hex = Offset::new(2, 2).into();
+        let neighbors: HashSet<Offset> = hex.neighbors().iter().map(|&n| n.into()).collect();
+        let expected: HashSet<Offset> = [(3, 2), (3, 3), (2, 3), (1, 3), (1, 2), (2, 1)]
+            .iter()
+            .map(|&(col, row)| Offset::new(col, row))
+            .collect();
+        assert_eq!(neighbors, expected);
+    }
+
+    #[test]
+    fn six_distinct_neighbors_one_step_away() {
+        for hex in sample() {
+            let neighbors = hex.neighbors();
+            let distinct: HashSet<Hex> = neighbors.iter().cloned().collect();
+            assert_eq!(distinct.len(), 6);
+            for (direction, &neighbor) in neighbors.iter().enumerate() {
+                assert_eq!(hex.distance(neighbor), 1);
+                assert_eq!(neighbor.neighbor(direction + 3), hex);
+            }
+        }
+    }
+
+    #[test]
+    fn distances_are_symmetric_and_count_steps() {
+        assert_eq!(Hex::new(0, 0).distance(Hex::new(3, -1)), 3);
+        assert_eq!(Hex::new(-2, 4).distance(Hex::new(2, -3)), 7);
+        for a in sample() {
+            assert_eq!(a.distance(a), 0);
+            for b in sample() {
+                assert_eq!(a.distance(b), b.distance(a));
+            }
+        }
+    }
+
+    #[test]
+    fn lines_run_between_their_ends_one_step_at_a_time() {
+        for &from in &sample() {
+            for &to in &[Hex::new(4, -2), Hex::new(-3, 0), from] {
+                let line: Vec<Hex> = from.line_to(to).collect();
+                assert_eq!(line.len() as u32, from.distance(to) + 1);
+                assert_eq!(line.first(), Some(&from));
+                assert_eq!(line.last(), Some(&to));
+                for pair in line.windows(2) {
+                    assert_eq!(pair[0].distance(pair[1]), 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rings_hold_six_hexes_per_step() {
+        let center = Hex::new(2, -1);
+        assert_eq!(center.ring(0).collect::<Vec<_>>(), vec![center]);
+        for radius in 1..6 {
+            let ring: Vec<Hex> = center.ring(radius).collect();
+            let distinct: HashSet<Hex> = ring.iter().cloned().collect();
+            assert_eq!(ring.len() as u32, 6 * radius);
+            assert_eq!(distinct.len(), ring.len());
+            assert!(ring.iter().all(|&hex| center.distance(hex) == radius));
+        }
+    }
+
+    #[test]
+    fn spirals_fill_the_hexagon() {
+        let center = Hex::new(-1, 3);
+        for radius in 0..6 {
+            let spiral: Vec<Hex> = center.spiral(radius).collect();
+            let distinct: HashSet<Hex> = spiral.iter().cloned().collect();
+            assert_eq!(spiral.len() as u32, 1 + 3 * radius * (radius + 1));
+            assert_eq!(distinct.len(), spiral.len());
+            assert!(spiral.iter().all(|&hex| center.distance(hex) <= radius));
+        }
+    }
+
+    #[test]
+    fn pixels_come_back_to_the_same_hex() {
+        let layout = Layout::BOARD;
+        for hex in sample() {
+            let (x, y) = layout.hex_to_pixel(hex);
+            assert_eq!(layout.pixel_to_hex(x, y), hex);
+            // and so does anywhere well inside the hex
+            for &(cx, cy) in layout.corners(hex).iter() {
+                let inside = (x + (cx - x) * 0.8, y + (cy - y) * 0.8);
+                assert_eq!(layout.pixel_to_hex(inside.0, inside.1), hex);
+            }
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{
+        Anchor, FontAsset, Interactable, Selectable, Selected, TextEditing, UiEvent, UiText,
+        UiTransform,
+    },
+};
+
+use crate::assets::HexAssets;
+use crate::board::PlayerId;
+use crate::gameplay::GameplayState;
+use crate::lobby::LobbyState;
+use crate::main_menu::MainMenuState;
+use crate::net::{self, Client, HostMessage, Lobby, NetError, Network};
+use crate::replay::Replay;
+
+/// The longest address or name that can be typed in.
+const MAX_FIELD_LENGTH: usize = 40;
+
+/// Connects to a host, and goes on to its lobby, or straight into its game when joining one that
+/// already started.
+pub struct JoinState {
+    address: String,
+    name: String,
+    /// what to say when the screen opens, if anything
+    status_text: String,
+    /// whether to join as soon as the screen opens
+    join_now: bool,
+    client: Option<Client>,
+    address_field: Option<Entity>,
+    name_field: Option<Entity>,
+    status: Option<Entity>,
+    join_button: Option<Entity>,
+    back_button: Option<Entity>,
+    my_ui: Vec<Entity>,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (join).";
+impl SimpleState for JoinState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        let title = create_title_text(world, &assets.font, "Join Game");
+        let address_label = create_label(world, &assets.font, "address", 60.0);
+        let address_field = create_field(world, &assets.font, "address", &self.address, 60.0, 0);
+        let name_label = create_label(world, &assets.font, "name", 0.0);
+        let name_field = create_field(world, &assets.font, "name", &self.name, 0.0, 1);
+        let status = create_status_text(world, &assets.font);
+        let back = create_button(world, &assets.font, "back", -150.0);
+        let join = create_button(world, &assets.font, "join", 150.0);
+        self.my_ui.extend(&[
+            title,
+            address_label,
+            address_field,
+            name_label,
+            name_field,
+            status,
+            back,
+            join,
+        ]);
+        self.address_field = Some(address_field);
+        self.name_field = Some(name_field);
+        self.status = Some(status);
+        self.join_button = Some(join);
+        self.back_button = Some(back);
+
+        let status = self.status_text.clone();
+        self.set_text(world, self.status, &status);
+        if self.join_now {
+            self.join(world);
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        self.my_ui.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            // keys go to the field being typed in
+            if self.is_typing(data.world) {
+                return Trans::None;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Switch(MainMenuState::new_boxed());
+            }
+            if is_key_down(&event, VirtualKeyCode::Return) {
+                self.join(data.world);
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click if Some(*target) == self.back_button => {
+                    return Trans::Switch(MainMenuState::new_boxed());
+                }
+                Click if Some(*target) == self.join_button => {
+                    self.join(data.world);
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let messages = match &self.client {
+            Some(client) => client.poll(),
+            None => return Trans::None,
+        };
+        for message in messages {
+            let status = match message {
+                Ok(HostMessage::Lobby { lobby, seat }) => {
+                    return self.enter_lobby(data.world, *lobby, seat)
+                }
+                Ok(HostMessage::Start { replay, you }) => {
+                    return self.start(data.world, *replay, you)
+                }
+                Ok(HostMessage::Refused(reason)) => NetError::Refused(reason).to_string(),
+                Ok(_) => continue,
+                Err(error) => format!("lost the host: {}", error),
+            };
+            self.client = None;
+            self.set_text(data.world, self.status, &status);
+        }
+        Trans::None
+    }
+}
+
+impl JoinState {
+    /// Join the host at `address` under `name` straight away, as asked for on the command line.
+    pub fn new(address: String, name: String) -> Self {
+        JoinState {
+            join_now: !address.is_empty(),
+            address,
+            name,
+            status_text: String::new(),
+            client: None,
+            address_field: None,
+            name_field: None,
+            status: None,
+            join_button: None,
+            back_button: None,
+            my_ui: vec![],
+        }
+    }
+    pub fn new_boxed(address: String, name: String) -> Box<Self> {
+        Box::new(JoinState::new(address, name))
+    }
+
+    /// Offer to join the host at `address` again under `name`, saying why with `status`. Joining
+    /// a game already started under the same name gets the same player back.
+    pub fn rejoin(address: String, name: String, status: String) -> Self {
+        JoinState {
+            join_now: false,
+            status_text: status,
+            ..JoinState::new(address, name)
+        }
+    }
+    pub fn rejoin_boxed(address: String, name: String, status: String) -> Box<Self> {
+        Box::new(JoinState::rejoin(address, name, status))
+    }
+
+    /// Connect to the address typed in, under the name typed in.
+    fn join(&mut self, world: &mut World) {
+        self.read_fields(world);
+        if self.address.is_empty() || self.name.is_empty() {
+            self.set_text(world, self.status, "type in the host's address and a name");
+            return;
+        }
+        let address = net::with_default_port(&self.address);
+        // a fresh connection replaces any earlier one
+        self.client = None;
+        let status = match Client::connect(&address, &self.name) {
+            Ok(client) => {
+                self.client = Some(client);
+                format!("joined {}, waiting for the host", address)
+            }
+            Err(error) => format!("couldn't join {}: {}", address, error),
+        };
+        self.set_text(world, self.status, &status);
+    }
+
+    /// Sit down at the host's table.
+    fn enter_lobby(&mut self, world: &mut World, lobby: Lobby, seat: usize) -> SimpleTrans {
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => return Trans::None,
+        };
+        world.insert(Network::Client { client });
+        Trans::Switch(LobbyState::joined_boxed(lobby, seat))
+    }
+
+    /// Set up the game the host sent, as it stands, and play it.
+    fn start(&mut self, world: &mut World, replay: Replay, you: PlayerId) -> SimpleTrans {
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => return Trans::None,
+        };
+        world.insert(Network::Client { client });
+        start_game(world, replay, you)
+    }
+
+    /// Copy what was typed into the fields.
+    fn read_fields(&mut self, world: &World) {
+        let texts = world.read_storage::<UiText>();
+        if let Some(text) = self.address_field.and_then(|field| texts.get(field)) {
+            self.address = text.text.trim().to_string();
+        }
+        if let Some(text) = self.name_field.and_then(|field| texts.get(field)) {
+            self.name = text.text.trim().to_string();
+        }
+    }
+
+    /// Whether a field has the keyboard.
+    fn is_typing(&self, world: &World) -> bool {
+        let selected = world.read_storage::<Selected>();
+        [self.address_field, self.name_field]
+            .iter()
+            .flatten()
+            .any(|&field| selected.contains(field))
+    }
+
+    fn set_text(&self, world: &mut World, entity: Option<Entity>, text: &str) {
+        if let Some(entity) = entity {
+            let mut texts = world.write_storage::<UiText>();
+            texts.get_mut(entity).expect(EXPECTED).text = text.to_string();
+        }
+    }
+}
+
+/// Set up the game a host sent, as it stands, and play it. The connection to the host must
+/// already be in the world.
+pub fn start_game(world: &mut World, replay: Replay, you: PlayerId) -> SimpleTrans {
+    world.insert(replay.game_at(replay.actions.len()));
+    world.insert(net::client_view(&replay.players, you));
+    world.insert(replay.seed);
+    world.insert(replay.settings);
+    world.insert(replay.setup);
+    Trans::Switch(GameplayState::new_boxed())
+}
+
+fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        -100.0,
+        1.0,
+        800.0,
+        75.0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            96.0,
+        ))
+        .build();
+
+    text
+}
+
+fn create_label(world: &mut World, font: &Handle<FontAsset>, text: &str, y: f32) -> Entity {
+    let transform = UiTransform::new(
+        format!("{} label", text),
+        Anchor::Middle,
+        Anchor::Middle,
+        -220.0,
+        y,
+        1.0,
+        140.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+/// An editable text box, for the address or the name.
+fn create_field(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    id: &str,
+    text: &str,
+    y: f32,
+    order: u32,
+) -> Entity {
+    let transform = UiTransform::new(
+        id.to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        60.0,
+        y,
+        1.0,
+        360.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .with(TextEditing::new(
+            MAX_FIELD_LENGTH,
+            [0.9, 0.9, 0.9, 1.0],
+            [0.1, 0.1, 0.1, 1.0],
+            false,
+        ))
+        .with(Interactable)
+        .with(Selectable::<()>::new(order))
+        .build()
+}
+
+fn create_status_text(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "status".to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        0.0,
+        -80.0,
+        1.0,
+        800.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+fn create_button(world: &mut World, font: &Handle<FontAsset>, text: &str, x: f32) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::BottomMiddle,
+        Anchor::Middle,
+        x,
+        30.0,
+        1.0,
+        300.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
@@ -0,0 +1,36 @@
+//! Hexadie, a territorial dice game. The game itself is in `main.rs`; everything it is built from
+//! lives here, so other tools like the `hexadie-sim` AI tournament can share it.
+
+pub mod ai;
+pub mod assets;
+pub mod board;
+pub mod board_view;
+pub mod camera;
+pub mod config;
+pub mod dice_view;
+pub mod entities;
+pub mod game;
+pub mod game_over;
+pub mod gameplay;
+pub mod handoff;
+pub mod hex;
+pub mod join;
+pub mod load_game;
+pub mod loading_screen;
+pub mod lobby;
+pub mod main_menu;
+pub mod mapgen;
+pub mod map_selection;
+pub mod net;
+pub mod new_game;
+pub mod picking;
+pub mod players;
+pub mod polygon;
+pub mod probability;
+pub mod replay;
+pub mod replay_screen;
+pub mod rules;
+pub mod save;
+pub mod seed;
+pub mod settings_screen;
+pub mod setup;
@@ -0,0 +1,259 @@
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, FontAsset, Interactable, UiEvent, UiText, UiTransform},
+    utils::application_root_dir,
+};
+
+use crate::assets::HexAssets;
+use crate::gameplay::GameplayState;
+use crate::main_menu::MainMenuState;
+use crate::replay::{self, Replay};
+use crate::replay_screen::watch;
+use crate::save::{self, SavedGame};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How many saves are listed, newest first.
+const MAX_SAVES: usize = 8;
+
+/// What is being listed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Listing {
+    Saves,
+    Replays,
+}
+
+/// Lists the saved games, and picks one up where it was left. Also lists replays, to watch one.
+pub struct LoadGameState {
+    listing: Listing,
+    saves: HashMap<Entity, PathBuf>,
+    back_button: Option<Entity>,
+    message: Option<Entity>,
+    my_ui: Vec<Entity>,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (load_game).";
+impl SimpleState for LoadGameState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        let (title, dir, none) = match self.listing {
+            Listing::Saves => ("Load Game", saves_dir(), "there are no saved games yet"),
+            Listing::Replays => ("Replays", replays_dir(), "there are no replays yet"),
+        };
+        let title = create_title_text(world, &assets.font, title);
+        let message = create_message_text(world, &assets.font);
+        let back = create_button(world, &assets.font, "back", Anchor::BottomMiddle, 30.0);
+        self.my_ui.extend(&[title, message, back]);
+        self.message = Some(message);
+        self.back_button = Some(back);
+
+        let saves = save::list_saves(&dir);
+        if saves.is_empty() {
+            self.set_text(world, self.message, none);
+        }
+        for (i, path) in saves.into_iter().take(MAX_SAVES).enumerate() {
+            let name = path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            let y = -200.0 - i as f32 * 50.0;
+            let button = create_button(world, &assets.font, &name, Anchor::TopMiddle, y);
+            self.saves.insert(button, path);
+            self.my_ui.push(button);
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        self.my_ui.clear();
+        self.saves.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        mut data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Switch(MainMenuState::new_boxed());
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click if Some(*target) == self.back_button => {
+                    return Trans::Switch(MainMenuState::new_boxed());
+                }
+                Click => {
+                    if let Some(path) = self.saves.get(target).cloned() {
+                        return self.open(&mut data.world, &path);
+                    }
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+}
+
+impl LoadGameState {
+    pub fn new() -> Self {
+        LoadGameState {
+            listing: Listing::Saves,
+            saves: HashMap::new(),
+            back_button: None,
+            message: None,
+            my_ui: vec![],
+        }
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(LoadGameState::new())
+    }
+    /// List the recorded replays instead of the saves.
+    pub fn replays_boxed() -> Box<Self> {
+        Box::new(LoadGameState {
+            listing: Listing::Replays,
+            ..LoadGameState::new()
+        })
+    }
+
+    /// Resume the save, or watch the replay, at `path`.
+    fn open(&mut self, world: &mut World, path: &Path) -> SimpleTrans {
+        let loaded = match self.listing {
+            Listing::Saves => SavedGame::load(path)
+                .map(|saved| resume(world, saved))
+                .map_err(|error| error.to_string()),
+            Listing::Replays => Replay::load(path)
+                .map(watch)
+                .map_err(|error| error.to_string()),
+        };
+        match loaded {
+            Ok(trans) => trans,
+            Err(error) => {
+                println!("failed to load {}: {}", path.display(), error);
+                self.set_text(world, self.message, &error);
+                Trans::None
+            }
+        }
+    }
+
+    fn set_text(&self, world: &mut World, entity: Option<Entity>, text: &str) {
+        if let Some(entity) = entity {
+            let mut texts = world.write_storage::<UiText>();
+            texts.get_mut(entity).expect(EXPECTED).text = text.to_string();
+        }
+    }
+}
+
+/// Put a saved game back in play.
+pub fn resume(world: &mut World, saved: SavedGame) -> SimpleTrans {
+    world.insert(saved.seed);
+    world.insert(saved.settings);
+    world.insert(saved.setup);
+    world.insert(saved.players);
+    world.insert(saved.game);
+    Trans::Switch(GameplayState::new_boxed())
+}
+
+/// Where saves are kept.
+pub fn saves_dir() -> PathBuf {
+    save::save_dir(&application_root_dir().expect("failed to find the game's folder"))
+}
+
+/// Where replays are kept.
+pub fn replays_dir() -> PathBuf {
+    replay::replay_dir(&application_root_dir().expect("failed to find the game's folder"))
+}
+
+fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        -100.0,
+        1.0,
+        800.0,
+        75.0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            96.0,
+        ))
+        .build();
+
+    text
+}
+
+fn create_message_text(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "message".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.0,
+        100.0,
+        1.0,
+        600.0,
+        30.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.6, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+fn create_button(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    anchor: Anchor,
+    y: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        anchor,
+        Anchor::Middle,
+        0.0,
+        y,
+        1.0,
+        300.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
@@ -16,7 +16,13 @@ use amethyst::{
 use crate::assets::HexAssets;
 use crate::config::GameSettings;
 use crate::entities::{create_camera, create_sprite};
+use crate::join::JoinState;
+use crate::lobby::LobbyState;
 use crate::main_menu::MainMenuState;
+use crate::net::{Host, Launch, Network};
+use crate::players::Palette;
+
+use std::collections::HashMap;
 
 /// The initial state will load any needed assets, and set them up in the world as needed. It will display a progress bar and loading text. Once loading is complete, we pass to the main menu state.
 #[derive(Default)]
@@ -25,6 +31,8 @@ pub struct InitialState {
     loading_sprites: Vec<Entity>,
     spritesheet: Option<Handle<SpriteSheet>>,
     keep_open: Option<Box<dyn Tracker>>, // amethyst does not expose the required type, using dynamic dispatch to get around this.
+    /// the network game asked for on the command line, if any
+    launch: Option<Launch>,
 }
 
 impl SimpleState for InitialState {
@@ -64,6 +72,10 @@ impl SimpleState for InitialState {
 
         let settings = load_asset(world, "config.ron", RonFormat, &mut self.progress);
 
+        world.insert(AssetStorage::<Palette>::new());
+
+        let palette = load_asset(world, "palette.ron", RonFormat, &mut self.progress);
+
         let font = load_asset(
             world,
             "kenneyfonts/Kenney Future Narrow.ttf",
@@ -80,6 +92,7 @@ impl SimpleState for InitialState {
             spritesheet: dice_spritesheet,
             font,
             settings,
+            palette,
             hex_sprites: hex_spritesheet,
             camera: camera,
         };
@@ -112,7 +125,16 @@ impl SimpleState for InitialState {
                         .expect("Tried to delete entities twice.");
                 }
 
-                Trans::Switch(MainMenuState::new_boxed())
+                match self.launch.take() {
+                    Some(Launch::Join(address, name)) => {
+                        Trans::Switch(JoinState::new_boxed(address, name))
+                    }
+                    Some(Launch::Host(address)) => {
+                        host(data.world, &address);
+                        Trans::Switch(LobbyState::hosting_boxed())
+                    }
+                    None => Trans::Switch(MainMenuState::new_boxed()),
+                }
             }
             Completion::Loading => {
                 let spritesheet = &self.spritesheet;
@@ -165,4 +187,23 @@ impl InitialState {
     pub fn new_boxed() -> Box<Self> {
         Box::new(InitialState::new())
     }
+    /// Start up, then host or join a network game.
+    pub fn with_launch(launch: Option<Launch>) -> Self {
+        InitialState {
+            launch,
+            ..Default::default()
+        }
+    }
+}
+
+/// Start listening for players to join. They sit down in the lobby.
+fn host(world: &mut World, address: &str) {
+    match Host::listen(address) {
+        // the lobby shows the address it is listening at
+        Ok(host) => world.insert(Network::Host {
+            host,
+            seated: HashMap::new(),
+        }),
+        Err(error) => println!("failed to host at {}: {}", address, error),
+    }
 }
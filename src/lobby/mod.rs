@@ -0,0 +1,758 @@
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, FontAsset, Interactable, UiEvent, UiText, UiTransform},
+};
+
+use crate::assets::HexAssets;
+use crate::config::load_settings;
+use crate::game::Game;
+use crate::gameplay::GameplayState;
+use crate::join::{self, JoinState};
+use crate::main_menu::MainMenuState;
+use crate::mapgen;
+use crate::net::{
+    self, ClientMessage, Host, HostEvent, HostMessage, Lobby, NetError, Network, PeerId, Seating,
+};
+use crate::players::load_palette;
+use crate::replay::Replay;
+use crate::seed::MapSeed;
+use crate::setup::Seat;
+
+use std::collections::HashMap;
+
+/// Where players meet before a network game: the host sets up the map and the seats, and
+/// everyone picks a color and says when they are ready. The host keeps the table, and clients see
+/// it as the host last sent it.
+pub struct LobbyState {
+    /// the table as it stands, or as the host last sent it
+    lobby: Option<Lobby>,
+    /// the seat played at this screen
+    seat: usize,
+    hosting: bool,
+    buttons: HashMap<Entity, LobbyAction>,
+    my_ui: Vec<Entity>,
+    /// a row per seat, rebuilt whenever the table changes
+    seat_rows: Vec<Entity>,
+    address_text: Option<Entity>,
+    map_text: Option<Entity>,
+    area_text: Option<Entity>,
+    count_text: Option<Entity>,
+    ready_button: Option<Entity>,
+    message: Option<Entity>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum LobbyAction {
+    SmallerMap,
+    BiggerMap,
+    SmallerAreas,
+    BiggerAreas,
+    Fewer,
+    More,
+    /// change who sits in a seat nobody joined
+    CycleSeat(usize),
+    /// send the client in a seat away
+    Kick(usize),
+    NextColor,
+    FillWithAi,
+    Ready,
+    Start,
+    Back,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (lobby).";
+impl SimpleState for LobbyState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        let title = create_title_text(world, &assets.font, "Lobby");
+        let address_text = create_text(world, &assets.font, "address", 0.0, -180.0, 600.0);
+        let map_text = create_text(world, &assets.font, "map", 0.0, -220.0, 300.0);
+        let area_text = create_text(world, &assets.font, "areas", 0.0, -260.0, 300.0);
+        let count_text = create_text(world, &assets.font, "count", 0.0, -300.0, 300.0);
+        let message = create_message_text(world, &assets.font);
+        self.my_ui.extend(&[
+            title,
+            address_text,
+            map_text,
+            area_text,
+            count_text,
+            message,
+        ]);
+        self.address_text = Some(address_text);
+        self.map_text = Some(map_text);
+        self.area_text = Some(area_text);
+        self.count_text = Some(count_text);
+        self.message = Some(message);
+
+        let mut buttons = vec![
+            (
+                "back",
+                LobbyAction::Back,
+                Anchor::BottomMiddle,
+                -300.0,
+                30.0,
+            ),
+            (
+                "color",
+                LobbyAction::NextColor,
+                Anchor::BottomMiddle,
+                -100.0,
+                30.0,
+            ),
+        ];
+        if self.hosting {
+            buttons.extend(vec![
+                (
+                    "< smaller",
+                    LobbyAction::SmallerMap,
+                    Anchor::TopMiddle,
+                    -250.0,
+                    -220.0,
+                ),
+                (
+                    "bigger >",
+                    LobbyAction::BiggerMap,
+                    Anchor::TopMiddle,
+                    250.0,
+                    -220.0,
+                ),
+                (
+                    "< smaller",
+                    LobbyAction::SmallerAreas,
+                    Anchor::TopMiddle,
+                    -250.0,
+                    -260.0,
+                ),
+                (
+                    "bigger >",
+                    LobbyAction::BiggerAreas,
+                    Anchor::TopMiddle,
+                    250.0,
+                    -260.0,
+                ),
+                (
+                    "< fewer",
+                    LobbyAction::Fewer,
+                    Anchor::TopMiddle,
+                    -250.0,
+                    -300.0,
+                ),
+                (
+                    "more >",
+                    LobbyAction::More,
+                    Anchor::TopMiddle,
+                    250.0,
+                    -300.0,
+                ),
+                (
+                    "fill with AI",
+                    LobbyAction::FillWithAi,
+                    Anchor::BottomMiddle,
+                    100.0,
+                    30.0,
+                ),
+                (
+                    "start",
+                    LobbyAction::Start,
+                    Anchor::BottomMiddle,
+                    300.0,
+                    30.0,
+                ),
+            ]);
+        } else {
+            buttons.push((
+                "ready",
+                LobbyAction::Ready,
+                Anchor::BottomMiddle,
+                100.0,
+                30.0,
+            ));
+        }
+        for (text, action, anchor, x, y) in buttons.into_iter() {
+            let button = create_button(world, &assets.font, text, anchor, x, y, 200.0);
+            self.buttons.insert(button, action);
+            self.my_ui.push(button);
+            if let LobbyAction::Ready = action {
+                self.ready_button = Some(button);
+            }
+        }
+
+        if self.hosting {
+            self.host(world);
+        }
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        data.world.delete_entities(&self.seat_rows).expect(EXPECTED);
+        self.my_ui.clear();
+        self.seat_rows.clear();
+        self.buttons.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        mut data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return self.perform(&mut data.world, LobbyAction::Back);
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click => {
+                    if let Some(action) = self.buttons.get(target).cloned() {
+                        return self.perform(&mut data.world, action);
+                    }
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if self.hosting {
+            self.poll_clients(data.world);
+            Trans::None
+        } else {
+            self.poll_host(data.world)
+        }
+    }
+}
+
+impl LobbyState {
+    /// Host a game, listening for clients on the default port unless already hosting.
+    pub fn hosting() -> Self {
+        LobbyState {
+            lobby: None,
+            seat: 0,
+            hosting: true,
+            buttons: HashMap::new(),
+            my_ui: vec![],
+            seat_rows: vec![],
+            address_text: None,
+            map_text: None,
+            area_text: None,
+            count_text: None,
+            ready_button: None,
+            message: None,
+        }
+    }
+    pub fn hosting_boxed() -> Box<Self> {
+        Box::new(LobbyState::hosting())
+    }
+
+    /// Sit at a host's table in `seat`, as it was sent. The connection to the host must already be
+    /// in the world.
+    pub fn joined(lobby: Lobby, seat: usize) -> Self {
+        LobbyState {
+            lobby: Some(lobby),
+            seat,
+            hosting: false,
+            ..LobbyState::hosting()
+        }
+    }
+    pub fn joined_boxed(lobby: Lobby, seat: usize) -> Box<Self> {
+        Box::new(LobbyState::joined(lobby, seat))
+    }
+
+    /// Start listening for clients, if nobody asked for it on the command line, and set the table.
+    fn host(&mut self, world: &mut World) {
+        let listening = match world.try_fetch::<Network>() {
+            Some(network) => match &*network {
+                Network::Host { host, .. } => Some(host.address()),
+                Network::Client { .. } => None,
+            },
+            None => None,
+        };
+        let address = match listening {
+            Some(address) => Ok(address),
+            None => {
+                let address = format!("0.0.0.0:{}", net::DEFAULT_PORT);
+                Host::listen(&address).map(|host| {
+                    let listening = host.address();
+                    world.insert(Network::Host {
+                        host,
+                        seated: HashMap::new(),
+                    });
+                    listening
+                })
+            }
+        };
+        let text = match address {
+            Ok(address) => format!("hosting at {}", address),
+            Err(error) => format!("couldn't host: {}", error),
+        };
+        self.set_text(world, self.address_text, &text);
+        let seats = load_palette(world).players.len();
+        self.lobby = Some(Lobby::new(load_settings(world), seats));
+    }
+
+    fn perform(&mut self, world: &mut World, action: LobbyAction) -> SimpleTrans {
+        if let LobbyAction::Back = action {
+            // leaving hangs up on everyone, or on the host
+            world.remove::<Network>();
+            return Trans::Switch(MainMenuState::new_boxed());
+        }
+        if !self.hosting {
+            let message = match action {
+                LobbyAction::NextColor => Some(ClientMessage::NextColor),
+                LobbyAction::Ready => {
+                    let ready = self.lobby.as_ref().and_then(|lobby| lobby.guest(self.seat));
+                    Some(ClientMessage::Ready(
+                        !ready.map_or(false, |guest| guest.ready),
+                    ))
+                }
+                _ => None,
+            };
+            if let (Some(message), Some(network)) = (message, world.try_fetch::<Network>()) {
+                if let Network::Client { client, .. } = &*network {
+                    // a host that can't be reached is noticed when its messages stop
+                    let _ = client.send(&message);
+                }
+            }
+            return Trans::None;
+        }
+
+        let lobby = match &mut self.lobby {
+            Some(lobby) => lobby,
+            None => return Trans::None,
+        };
+        let mut sent_away = vec![];
+        match action {
+            LobbyAction::SmallerMap => lobby.resize_map(-1),
+            LobbyAction::BiggerMap => lobby.resize_map(1),
+            LobbyAction::SmallerAreas => lobby.resize_areas(-1),
+            LobbyAction::BiggerAreas => lobby.resize_areas(1),
+            LobbyAction::Fewer => {
+                let count = lobby.setup.seats.len() - 1;
+                sent_away = lobby.set_seat_count(count);
+            }
+            LobbyAction::More => {
+                let count = lobby.setup.seats.len() + 1;
+                lobby.set_seat_count(count);
+            }
+            LobbyAction::CycleSeat(seat) => lobby.cycle_seat(seat),
+            LobbyAction::Kick(seat) => {
+                if let Some(peer) = lobby.guest(seat).map(|guest| guest.peer) {
+                    lobby.leave(peer);
+                    sent_away.push(peer);
+                }
+            }
+            LobbyAction::NextColor => lobby.setup.next_color(self.seat),
+            LobbyAction::FillWithAi => lobby.fill_with_ai(),
+            LobbyAction::Start => return self.start(world),
+            LobbyAction::Ready | LobbyAction::Back => {}
+        }
+        with_host(world, |host| {
+            for peer in sent_away {
+                host.refuse(peer, "the host sent you away");
+            }
+        });
+        self.set_text(world, self.message, "");
+        self.share(world);
+        self.refresh(world);
+        Trans::None
+    }
+
+    /// Take in whoever joined, left or said something since the last update, when hosting.
+    fn poll_clients(&mut self, world: &mut World) {
+        let mut events = vec![];
+        with_host(world, |host| events = host.poll());
+        if events.is_empty() {
+            return;
+        }
+        let palette = load_palette(world);
+        let lobby = match &mut self.lobby {
+            Some(lobby) => lobby,
+            None => return,
+        };
+        let mut refused: Vec<(PeerId, String)> = vec![];
+        let mut message = None;
+        for event in events {
+            match event {
+                HostEvent::Joined(peer, name) => match lobby.join(peer, &name, &palette) {
+                    Ok(_) => message = Some(format!("{} joined", name.trim())),
+                    Err(reason) => refused.push((peer, reason)),
+                },
+                HostEvent::Left(peer) => {
+                    if let Some(seat) = lobby.seat_of(peer) {
+                        let name = lobby.setup.name(seat).unwrap_or("someone").to_string();
+                        message = Some(format!("{} left", name));
+                        lobby.leave(peer);
+                    }
+                }
+                HostEvent::Message(peer, ClientMessage::Ready(ready)) => {
+                    lobby.set_ready(peer, ready);
+                }
+                HostEvent::Message(peer, ClientMessage::NextColor) => {
+                    if let Some(seat) = lobby.seat_of(peer) {
+                        lobby.setup.next_color(seat);
+                    }
+                }
+                HostEvent::Message(..) => {}
+            }
+        }
+        with_host(world, |host| {
+            for (peer, reason) in refused {
+                host.refuse(peer, &reason);
+            }
+        });
+        if let Some(message) = message {
+            self.set_text(world, self.message, &message);
+        }
+        self.share(world);
+        self.refresh(world);
+    }
+
+    /// Take in what the host sent since the last update, when joined to one.
+    fn poll_host(&mut self, world: &mut World) -> SimpleTrans {
+        let (messages, address, name) = match world.try_fetch::<Network>() {
+            Some(network) => match &*network {
+                Network::Client { client, .. } => (
+                    client.poll(),
+                    client.address().to_string(),
+                    client.name().to_string(),
+                ),
+                Network::Host { .. } => return Trans::None,
+            },
+            None => return Trans::None,
+        };
+        for message in messages {
+            let status = match message {
+                Ok(HostMessage::Lobby { lobby, seat }) => {
+                    self.lobby = Some(*lobby);
+                    self.seat = seat;
+                    self.refresh(world);
+                    continue;
+                }
+                Ok(HostMessage::Start { replay, you }) => {
+                    return join::start_game(world, *replay, you);
+                }
+                Ok(HostMessage::Refused(reason)) => NetError::Refused(reason).to_string(),
+                Ok(_) => continue,
+                Err(error) => format!("lost the host: {}", error),
+            };
+            world.remove::<Network>();
+            return Trans::Switch(JoinState::rejoin_boxed(address, name, status));
+        }
+        Trans::None
+    }
+
+    /// Send everyone the table as it stands, when hosting.
+    fn share(&self, world: &World) {
+        let lobby = match &self.lobby {
+            Some(lobby) => lobby,
+            None => return,
+        };
+        with_host(world, |host| {
+            for seat in 0..lobby.guests.len() {
+                if let Some(guest) = lobby.guest(seat) {
+                    let message = HostMessage::Lobby {
+                        lobby: Box::new(lobby.clone()),
+                        seat,
+                    };
+                    host.send(guest.peer, &message);
+                }
+            }
+        });
+    }
+
+    /// Generate the map, send every client the game and which player they are, and play it.
+    fn start(&mut self, world: &mut World) -> SimpleTrans {
+        let lobby = match &self.lobby {
+            Some(lobby) => lobby.clone(),
+            None => return Trans::None,
+        };
+        let Seating {
+            setup,
+            players,
+            seated,
+        } = match lobby.start(&load_palette(world)) {
+            Ok(seating) => seating,
+            Err(reason) => {
+                self.set_text(world, self.message, &reason);
+                return Trans::None;
+            }
+        };
+
+        let mut settings = lobby.settings;
+        settings.player_count = Some(setup.player_count() as u32);
+        let seed = settings.seed.map(MapSeed).unwrap_or_else(MapSeed::random);
+        let board = mapgen::generate(&settings, &mut seed.rng()).board;
+        let game = Game::new(board, setup.turn_order(), seed.game_rng());
+        let replay = Replay::record(
+            seed,
+            settings.clone(),
+            setup.clone(),
+            players.clone(),
+            &game,
+        );
+        with_host(world, |host| {
+            for (&peer, &you) in seated.iter() {
+                let replay = Box::new(replay.clone());
+                host.send(peer, &HostMessage::Start { replay, you });
+            }
+        });
+
+        if let Some(mut network) = world.try_fetch_mut::<Network>() {
+            if let Network::Host {
+                seated: playing, ..
+            } = &mut *network
+            {
+                *playing = seated;
+            }
+        }
+        world.insert(settings);
+        world.insert(setup);
+        world.insert(players);
+        world.insert(game);
+        world.insert(seed);
+        Trans::Switch(GameplayState::new_boxed())
+    }
+
+    /// Rebuild the seat rows and captions to match the table.
+    fn refresh(&mut self, world: &mut World) {
+        let lobby = match &self.lobby {
+            Some(lobby) => lobby.clone(),
+            None => return,
+        };
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        for entity in self.seat_rows.drain(..) {
+            self.buttons.remove(&entity);
+            world.delete_entity(entity).expect(EXPECTED);
+        }
+        let palette = load_palette(world);
+        for (i, seat) in lobby.setup.seats.iter().enumerate() {
+            let y = -350.0 - i as f32 * 36.0;
+            let color = palette
+                .players
+                .get(lobby.setup.color(i))
+                .map_or((0.5, 0.5, 0.5), |entry| entry.color);
+            let mut name = lobby.seat_name(i, &palette);
+            if i == self.seat {
+                name.push_str(" (you)");
+            }
+            let name = create_seat_name(world, &assets.font, &name, i, color, y);
+            self.seat_rows.push(name);
+
+            let state = match (i, lobby.guest(i)) {
+                (0, _) => "host".to_string(),
+                (_, Some(guest)) if guest.ready => "ready".to_string(),
+                (_, Some(_)) => "not ready".to_string(),
+                (_, None) if *seat == Seat::Human => "open".to_string(),
+                (_, None) => seat.to_string(),
+            };
+            let anchor = Anchor::TopMiddle;
+            let state = create_button(world, &assets.font, &state, anchor, 60.0, y, 200.0);
+            self.seat_rows.push(state);
+            if !self.hosting || i == 0 {
+                continue;
+            }
+            if lobby.guest(i).is_some() {
+                let kick = create_button(world, &assets.font, "kick", anchor, 260.0, y, 120.0);
+                self.buttons.insert(kick, LobbyAction::Kick(i));
+                self.seat_rows.push(kick);
+            } else {
+                self.buttons.insert(state, LobbyAction::CycleSeat(i));
+            }
+        }
+
+        let settings = &lobby.settings;
+        let map = format!("map of {} x {} hexes", settings.width, settings.height);
+        let areas = format!("territories of {} hexes", settings.area_size);
+        let count = format!(
+            "{} of {} seats",
+            lobby.setup.seats.len(),
+            lobby.setup.max_seats
+        );
+        self.set_text(world, self.map_text, &map);
+        self.set_text(world, self.area_text, &areas);
+        self.set_text(world, self.count_text, &count);
+        if !self.hosting {
+            let ready = lobby.guest(self.seat).map_or(false, |guest| guest.ready);
+            let ready = if ready { "not ready" } else { "ready" };
+            self.set_text(world, self.ready_button, ready);
+        }
+    }
+
+    fn set_text(&self, world: &mut World, entity: Option<Entity>, text: &str) {
+        if let Some(entity) = entity {
+            let mut texts = world.write_storage::<UiText>();
+            texts.get_mut(entity).expect(EXPECTED).text = text.to_string();
+        }
+    }
+}
+
+/// Do something with the connections to clients, when hosting.
+fn with_host(world: &World, f: impl FnOnce(&Host)) {
+    if let Some(network) = world.try_fetch::<Network>() {
+        if let Network::Host { host, .. } = &*network {
+            f(host);
+        }
+    }
+}
+
+fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        -100.0,
+        1.0,
+        800.0,
+        75.0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            96.0,
+        ))
+        .build();
+
+    text
+}
+
+fn create_text(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    id: &str,
+    x: f32,
+    y: f32,
+    width: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        id.to_string(),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        x,
+        y,
+        1.0,
+        width,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+fn create_message_text(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "message".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.0,
+        80.0,
+        1.0,
+        600.0,
+        30.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.6, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+fn create_button(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    anchor: Anchor,
+    x: f32,
+    y: f32,
+    width: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        anchor,
+        Anchor::Middle,
+        x,
+        y,
+        1.0,
+        width,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+/// A seat's name, written in the seat's color.
+fn create_seat_name(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    seat: usize,
+    color: (f32, f32, f32),
+    y: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        format!("seat {}", seat),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        -220.0,
+        y,
+        1.0,
+        300.0,
+        40.0,
+    );
+
+    let (r, g, b) = color;
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [r, g, b, 1.0],
+            20.0,
+        ))
+        .build()
+}
@@ -14,15 +14,14 @@ use amethyst::{
     LogLevelFilter, LoggerConfig, StdoutLog,
 };
 
-mod assets;
-mod config;
-mod entities;
-mod loading_screen;
-mod main_menu;
-mod map_selection;
-mod settings_screen;
-
-use crate::config::{GameSettings, GameSettingsBundle};
+use hexadie::ai::AiSystem;
+use hexadie::camera::CameraControlSystem;
+use hexadie::config::{GameSettings, GameSettingsBundle};
+use hexadie::dice_view::BoardLabelSystem;
+use hexadie::loading_screen;
+use hexadie::net::{self, Launch};
+use hexadie::picking::HexPickingSystem;
+use hexadie::players::Palette;
 
 fn main() -> amethyst::Result<()> {
     let app_root = application_root_dir()?;
@@ -63,14 +62,61 @@ fn main() -> amethyst::Result<()> {
                     RenderToWindow::from_config(display_config).with_clear([0.68, 0.78, 0.76, 1.0]),
                 )
                 .with_plugin(RenderFlat2D::default())
+                .with_plugin(RenderFlat3D::default())
                 .with_plugin(RenderUi::default())
         )?
         .with_bundle(InputBundle::<StringBindings>::new())?
         .with_bundle(UiBundle::<StringBindings>::new())?
-        .with(Processor::<GameSettings>::new(), "settings_processor", &[]);
+        .with(Processor::<GameSettings>::new(), "settings_processor", &[])
+        .with(Processor::<Palette>::new(), "palette_processor", &[])
+        .with(HexPickingSystem::default(), "hex_picking", &["input_system"])
+        .with(CameraControlSystem, "camera_control", &["input_system"])
+        .with(BoardLabelSystem, "board_labels", &["camera_control"])
+        .with(AiSystem::default(), "ai", &[]);
 
-    let mut game = Application::new(assets_path, loading_screen::InitialState::new(), game_data)?;
+    let initial = loading_screen::InitialState::with_launch(launch());
+    let mut game = Application::new(assets_path, initial, game_data)?;
     game.run();
 
     Ok(())
 }
+
+/// Read the network game asked for on the command line:
+///
+///     hexadie --host [port or address]
+///     hexadie --join <address> [--name <name>]
+fn launch() -> Option<Launch> {
+    let mut launch = None;
+    let mut name = String::new();
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => {
+                let given = if args.peek().map_or(false, |next| !next.starts_with("--")) {
+                    args.next()
+                } else {
+                    None
+                };
+                let address = match given {
+                    Some(port) if port.parse::<u16>().is_ok() => format!("0.0.0.0:{}", port),
+                    Some(address) => net::with_default_port(&address),
+                    None => format!("0.0.0.0:{}", net::DEFAULT_PORT),
+                };
+                launch = Some(Launch::Host(address));
+            }
+            "--join" => match args.next() {
+                Some(address) => {
+                    let address = net::with_default_port(&address);
+                    launch = Some(Launch::Join(address, String::new()));
+                }
+                None => println!("--join needs the address of the host"),
+            },
+            "--name" => name = args.next().unwrap_or_default(),
+            _ => println!("ignoring unknown argument {}", arg),
+        }
+    }
+    match launch {
+        Some(Launch::Join(address, _)) => Some(Launch::Join(address, name)),
+        launch => launch,
+    }
+}
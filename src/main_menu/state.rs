@@ -15,7 +15,11 @@ use amethyst::{
 use crate::assets::HexAssets;
 use crate::config::GameSettings;
 use crate::entities::create_sprite;
-use crate::map_selection::MapSelectionState;
+use crate::join::JoinState;
+use crate::load_game::{resume, saves_dir, LoadGameState};
+use crate::lobby::LobbyState;
+use crate::new_game::NewGameState;
+use crate::save::{SavedGame, AUTOSAVE};
 use crate::settings_screen::SettingsState;
 
 use std::collections::HashMap;
@@ -34,8 +38,17 @@ impl SimpleState for MainMenuState {
 
         let title = create_title_text(world, &assets.font, "HEXADIE");
         self.my_ui.push(title);
-        let menu = MenuBuilder::new(50.0, &assets.font)
+        let mut menu = MenuBuilder::new(50.0, &assets.font);
+        // only offer to continue if there is a game to go back to
+        if saves_dir().join(AUTOSAVE).exists() {
+            menu = menu.add_button(world, "continue", Box::new(continue_game));
+        }
+        let menu = menu
             .add_button(world, "new game", Box::new(new_game))
+            .add_button(world, "load game", Box::new(load_game))
+            .add_button(world, "host game", Box::new(host_game))
+            .add_button(world, "join game", Box::new(join_game))
+            .add_button(world, "replays", Box::new(replays))
             .add_button(world, "settings", Box::new(settings))
             .add_button(world, "quit", Box::new(quit));
         self.my_ui.append(&mut menu.get_entities());
@@ -118,9 +131,35 @@ impl MainMenuState {
 
 type MenuFunction = Box<fn(world: &mut World) -> SimpleTrans>;
 
+fn continue_game(world: &mut World) -> SimpleTrans {
+    let path = saves_dir().join(AUTOSAVE);
+    match SavedGame::load(&path) {
+        Ok(saved) => resume(world, saved),
+        Err(error) => {
+            println!("failed to continue from {}: {}", path.display(), error);
+            Trans::None
+        }
+    }
+}
+
 fn new_game(_w: &mut World) -> SimpleTrans {
-    let map = MapSelectionState::new_boxed();
-    Trans::Switch(map)
+    Trans::Switch(NewGameState::new_boxed())
+}
+
+fn load_game(_w: &mut World) -> SimpleTrans {
+    Trans::Switch(LoadGameState::new_boxed())
+}
+
+fn host_game(_w: &mut World) -> SimpleTrans {
+    Trans::Switch(LobbyState::hosting_boxed())
+}
+
+fn join_game(_w: &mut World) -> SimpleTrans {
+    Trans::Switch(JoinState::new_boxed(String::new(), String::new()))
+}
+
+fn replays(_w: &mut World) -> SimpleTrans {
+    Trans::Switch(LoadGameState::replays_boxed())
 }
 
 fn settings(_w: &mut World) -> SimpleTrans {
@@ -1,64 +1,170 @@
 use amethyst::{
-    assets::{AssetStorage, Handle, Loader},
-    core::{
-        ecs::{Component, DenseVecStorage, Entities, Entity, Join, ReadStorage, WriteStorage},
-        transform::Transform,
-    },
-    input::{get_key, is_close_requested, is_key_down, VirtualKeyCode},
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
     prelude::*,
-    renderer::{
-        palette::Srgb, resources::Tint, Camera, Material, MaterialDefaults, Mesh, SpriteRender,
-        SpriteSheet, Transparent,
-    },
     ui::{
-        Anchor, FontAsset, Interactable, TtfFormat, UiButtonBuilder, UiEvent, UiText, UiTransform,
+        Anchor, FontAsset, Interactable, Selectable, Selected, TextEditing, UiEvent, UiText,
+        UiTransform,
     },
-    window::ScreenDimensions,
 };
 
-use rand::{thread_rng, Rng};
+use crate::assets::HexAssets;
+use crate::board::Board;
+use crate::board_view::BoardView;
+use crate::config::{load_settings, GameSettings};
+use crate::dice_view::BoardLabel;
+use crate::entities::point_camera;
+use crate::game::Game;
+use crate::gameplay::GameplayState;
+use crate::hex::Layout;
+use crate::main_menu::MainMenuState;
+use crate::mapgen;
+use crate::players::{load_palette, Players};
+use crate::seed::MapSeed;
+use crate::setup::GameSetup;
+
 use std::collections::HashMap;
 
-use crate::assets::HexAssets;
-use crate::config::GameSettings;
-use crate::entities::create_sprite;
+/// How many maps to offer the player at once.
+const CANDIDATES: usize = 4;
+/// The previews are laid out in a grid this many maps across, around the middle of the screen.
+const PREVIEW_COLUMNS: usize = 2;
+/// The room each preview gets, in world units, including its label.
+const PREVIEW_CELL: (f32, f32) = (760.0, 520.0);
+const LABEL_HEIGHT: f32 = 40.0;
+
+/// Offers a handful of generated maps to choose from before a game starts.
+pub struct MapSelectionState {
+    candidates: Vec<(MapSeed, Board)>,
+    selected: usize,
+    /// one preview per candidate, side by side
+    views: Vec<BoardView>,
+    /// the label under each preview
+    labels: Vec<Entity>,
+    buttons: HashMap<Entity, MapAction>,
+    my_ui: Vec<Entity>,
+    caption: Option<Entity>,
+    seed_field: Option<Entity>,
+    message: Option<Entity>,
+}
 
-pub struct MapSelectionState {}
+#[derive(Copy, Clone, Debug)]
+enum MapAction {
+    Previous,
+    Next,
+    Reroll,
+    Play,
+}
 
 impl SimpleState for MapSelectionState {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        let mut world = data.world;
-        let _dimensions = (*world.read_resource::<ScreenDimensions>()).clone();
+        let world = data.world;
         let assets = (*world.read_resource::<HexAssets>()).clone();
 
-        world.register::<Hexagon>();
-        world.register::<Player>();
+        let title = create_title_text(world, &assets.font, "Map Selection");
+        let caption = create_caption_text(world, &assets.font);
+        let seed_field = create_seed_field(world, &assets.font);
+        let message = create_message_text(world, &assets.font);
+        self.my_ui.extend(&[title, caption, seed_field, message]);
+        self.caption = Some(caption);
+        self.seed_field = Some(seed_field);
+        self.message = Some(message);
+
+        let buttons = [
+            ("< previous", MapAction::Previous),
+            ("next >", MapAction::Next),
+            ("reroll", MapAction::Reroll),
+            ("play", MapAction::Play),
+        ];
+        for (i, (text, action)) in buttons.iter().enumerate() {
+            let x = (i as f32 - 1.5) * 190.0;
+            let button = create_button(world, &assets.font, text, x, 100.0);
+            self.buttons.insert(button, *action);
+            self.my_ui.push(button);
+        }
 
-        create_title_text(world, &assets.font, "Map Selection");
-        create_map(world);
+        // coming here without the new game screen plays with the default seats
+        if world.try_fetch::<GameSetup>().is_none() {
+            let seats = load_palette(world).players.len();
+            let setup = GameSetup::from_settings(&load_settings(world), seats);
+            world.insert(setup);
+        }
+        let players = Players::from_setup(&game_setup(world), &load_palette(world));
+        world.insert(players);
+
+        // the configured seed, if there is one, is always offered first
+        let first = load_settings(world).seed.map(MapSeed);
+        self.reroll(world, first);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.despawn_previews(data.world);
+        data.world
+            .delete_entities(&self.my_ui)
+            .expect("map selection ui was deleted twice");
+        self.my_ui.clear();
+        self.buttons.clear();
     }
 
     fn handle_event(
         &mut self,
-        mut _data: StateData<'_, GameData<'_, '_>>,
+        mut data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
         if let StateEvent::Window(event) = &event {
-            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+            if is_close_requested(&event) {
                 return Trans::Quit;
             }
+            // keys go to the seed field while it is being typed in
+            if self.is_typing(data.world) {
+                return Trans::None;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Switch(MainMenuState::new_boxed());
+            }
 
-            if let Some(_event) = get_key(&event) {
-                //
+            let keys = [
+                (VirtualKeyCode::Left, MapAction::Previous),
+                (VirtualKeyCode::Right, MapAction::Next),
+                (VirtualKeyCode::R, MapAction::Reroll),
+                (VirtualKeyCode::Return, MapAction::Play),
+            ];
+            for (key, action) in keys.iter() {
+                if is_key_down(&event, *key) {
+                    return self.perform(&mut data.world, *action);
+                }
             }
         }
         if let StateEvent::Ui(event) = &event {
-            println!("got ui event");
             use amethyst::ui::UiEventType::*;
             let UiEvent { event_type, target } = event;
             match event_type {
+                ValueCommit if Some(*target) == self.seed_field => {
+                    let typed = data
+                        .world
+                        .read_storage::<UiText>()
+                        .get(*target)
+                        .map(|text| text.text.clone())
+                        .unwrap_or_default();
+                    match typed.parse::<MapSeed>() {
+                        Ok(seed) => {
+                            // a typed seed replaces the map being previewed
+                            let board = generate(&mut data.world, seed);
+                            self.candidates[self.selected] = (seed, board);
+                            self.set_message(&mut data.world, "");
+                            self.show_candidates(&mut data.world);
+                        }
+                        Err(_) => {
+                            let message = format!("\"{}\" is not a map seed", typed.trim());
+                            self.set_message(&mut data.world, &message);
+                        }
+                    }
+                }
                 Click => {
-                    println!("Clicked! {:?}", target);
+                    if let Some(action) = self.buttons.get(target).cloned() {
+                        return self.perform(&mut data.world, action);
+                    }
                 }
                 HoverStart => {}
                 HoverStop => {}
@@ -73,11 +179,124 @@ impl SimpleState for MapSelectionState {
 
 impl MapSelectionState {
     pub fn new() -> Self {
-        MapSelectionState {}
+        MapSelectionState {
+            candidates: vec![],
+            selected: 0,
+            views: vec![],
+            labels: vec![],
+            buttons: HashMap::new(),
+            my_ui: vec![],
+            caption: None,
+            seed_field: None,
+            message: None,
+        }
     }
     pub fn new_boxed() -> Box<Self> {
         Box::new(MapSelectionState::new())
     }
+
+    fn perform(&mut self, world: &mut World, action: MapAction) -> SimpleTrans {
+        match action {
+            MapAction::Previous => {
+                self.selected = (self.selected + CANDIDATES - 1) % CANDIDATES;
+                self.show_selected(world);
+            }
+            MapAction::Next => {
+                self.selected = (self.selected + 1) % CANDIDATES;
+                self.show_selected(world);
+            }
+            MapAction::Reroll => {
+                self.reroll(world, None);
+            }
+            MapAction::Play => {
+                let (seed, board) = self.candidates[self.selected].clone();
+                let turn_order = game_setup(world).turn_order();
+                world.insert(Game::new(board, turn_order, seed.game_rng()));
+                // the settings played with are the ones of the map picked, not of config.ron
+                let mut settings = map_settings(world);
+                settings.seed = Some(seed.0);
+                world.insert(settings);
+                world.insert(seed);
+                return Trans::Switch(GameplayState::new_boxed());
+            }
+        }
+        Trans::None
+    }
+
+    /// Replace every candidate with a freshly generated map, starting with `first` if given.
+    fn reroll(&mut self, world: &mut World, first: Option<MapSeed>) {
+        self.candidates = (0..CANDIDATES)
+            .map(|i| {
+                let seed = match first {
+                    Some(seed) if i == 0 => seed,
+                    _ => MapSeed::random(),
+                };
+                (seed, generate(world, seed))
+            })
+            .collect();
+        self.selected = 0;
+        self.show_candidates(world);
+    }
+
+    /// Preview every candidate side by side, replacing any maps already on screen.
+    fn show_candidates(&mut self, world: &mut World) {
+        self.despawn_previews(world);
+        let font = world.read_resource::<HexAssets>().font.clone();
+        for (i, (_, board)) in self.candidates.iter().enumerate() {
+            let (layout, (x, y)) = preview_layout(board, i);
+            self.views
+                .push(BoardView::spawn_with_layout(world, board, &layout));
+            self.labels.push(create_preview_label(world, &font, x, y));
+        }
+        point_camera(world, 0.0, 0.0);
+        self.show_selected(world);
+    }
+
+    /// Mark the selected candidate, and show its seed.
+    fn show_selected(&mut self, world: &mut World) {
+        let seed = self.candidates[self.selected].0;
+        let caption = format!("map {} of {}", self.selected + 1, CANDIDATES);
+        let mut texts = world.write_storage::<UiText>();
+        for (entity, text) in &[(self.caption, caption), (self.seed_field, seed.to_string())] {
+            if let Some(ui_text) = entity.and_then(|entity| texts.get_mut(entity)) {
+                ui_text.text = text.clone();
+            }
+        }
+        for (i, &label) in self.labels.iter().enumerate() {
+            if let Some(ui_text) = texts.get_mut(label) {
+                ui_text.text = if i == self.selected {
+                    format!("> map {} <", i + 1)
+                } else {
+                    format!("map {}", i + 1)
+                };
+            }
+        }
+    }
+
+    fn despawn_previews(&mut self, world: &mut World) {
+        for view in self.views.drain(..) {
+            view.despawn(world);
+        }
+        world
+            .delete_entities(&self.labels)
+            .expect("map selection ui was deleted twice");
+        self.labels.clear();
+    }
+
+    /// Whether the seed field has the keyboard.
+    fn is_typing(&self, world: &World) -> bool {
+        let selected = world.read_storage::<Selected>();
+        self.seed_field
+            .map_or(false, |field| selected.contains(field))
+    }
+
+    fn set_message(&self, world: &mut World, message: &str) {
+        if let Some(entity) = self.message {
+            if let Some(text) = world.write_storage::<UiText>().get_mut(entity) {
+                text.text = message.to_string();
+            }
+        }
+    }
 }
 
 fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
@@ -106,215 +325,179 @@ fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) ->
     text
 }
 
-fn create_map(world: &mut World) {
-    let assets = (*world.read_resource::<HexAssets>()).clone();
-
-    let mut settings: GameSettings = {
-        let handle = &assets.settings;
-        let asset_storage = world.read_resource::<AssetStorage<GameSettings>>();
-        asset_storage
-            .get(handle)
-            .expect("failed to load settings")
-            .clone()
-    };
-
-    let player_count = settings.player_count.unwrap_or(8);
-    let territory_count = player_count * 4;
-
-    let width = settings.width;
-    let height = settings.height;
-
-    let area_size = if settings.area_size == 0 {
-        1
-    } else {
-        settings.area_size
-    };
-
-    let hex_count = territory_count * settings.area_size;
-    let overall_area = width * height;
-
-    let hex_offset_y = 56.0;
-    let hex_offset_x = 49.0;
-    let scale = 1.0;
-
-    {
-        let camera = assets.camera;
-        world.exec(
-            |(mut transforms, cameras): (WriteStorage<Transform>, ReadStorage<Camera>)| {
-                for (mut transform, camera) in (&mut transforms, &cameras).join() {
-                    let width = width as f32 * hex_offset_x * scale;
-                    let height = height as f32 * hex_offset_y * scale;
-                    transform.set_translation_xyz(width / 2.0, height / 2.0, 1.0);
-                }
-            },
-        );
-    }
-
-    println!(
-        "making map {}x{} = {}, with {} hexes in {} territories({}-sized)",
-        width, height, overall_area, hex_count, territory_count, area_size
+/// An editable text box showing the map seed. Typing a seed and pressing enter regenerates the map.
+fn create_seed_field(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "seed".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.0,
+        20.0,
+        1.0,
+        300.0,
+        40.0,
     );
 
-    // generate a number of hexagon entities
-    // these will be combined into groups of area_size hexes
-    // called areas
-    let mut hex_list = vec![];
-    // world.exec(|(mut entities, mut hexagons): (Entities, WriteStorage<Hexagon>)| {
-    for x in 0..width {
-        for y in 0..height {
-            let even_column_offset: (f32, f32) = if x % 2 == 0 { (-0.5, 28.0) } else { (0.0, 0.0) };
-            let x = x as f32;
-            let y = y as f32;
-
-            // entities.build_entity()
-            let sprite_sheet_handle = assets.hex_sprites.clone();
-
-            let sprite_render = SpriteRender {
-                sprite_sheet: sprite_sheet_handle,
-                sprite_number: 0,
-            };
-            let mut transform = Transform::default();
-            let x = (x * hex_offset_x + even_column_offset.0) * scale;
-            let y = (y * hex_offset_y + even_column_offset.1) * scale;
-            transform.set_translation_xyz(x, y, 0.0);
-            transform.set_scale([scale, scale, scale].into());
-            // transform.set_rotation_2d(rotation / 180.0 * PI);
-
-            let hexagon = Hexagon {
-                x: x,
-                y: y,
-                init: None,
-            };
-
-            let mut hex_builder = world
-                .create_entity()
-                .with(sprite_render)
-                .with(transform)
-                .with(hexagon)
-                .with(Tint(Srgb::new(0.2, 0.2, 0.2).into()))
-                .with(Transparent);
-            // if let Some((r, g, b)) = tint {
-            // hex_builder = hex_builder.with(Tint(Srgb::new(0.2, 0.2, 0.2).into()));
-            // }
-            let hex = hex_builder.build();
-            hex_list.push(hex);
-        }
-    }
-
-    // randomly select territory_count hexes to seed the map
-    let mut rng = thread_rng();
-    let mut players = world.write_storage::<Player>();
-    println!("randomly placing initial areas");
-    let mut areas: HashMap<Entity, (u32, Player, bool)> = HashMap::new();
-    for country in 0..territory_count {
-        let x = rng.gen_range(0, width) as usize;
-        let y = rng.gen_range(0, height) as usize;
-        println!(
-            "picking area {},{}, which is index {} out of {}",
-            x,
-            y,
-            y * width as usize + x,
-            hex_list.len()
-        );
-        let hex = hex_list.get(y * width as usize + x).unwrap();
-        println!("initializing area {}", country);
-
-        let player = match country % player_count {
-            0 => Player::One,
-            1 => Player::Two,
-            2 => Player::Three,
-            3 => Player::Four,
-            4 => Player::Five,
-            5 => Player::Six,
-            6 => Player::Seven,
-            7 => Player::Eight,
-            // 8 => Player::Nine,
-            _ => panic!("modulo overran allowed player count"),
-        };
-        players.insert(*hex, player);
-        areas.insert(*hex, (1, player, false));
-    }
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            32.0,
+        ))
+        .with(TextEditing::new(
+            16,
+            [0.9, 0.9, 0.9, 1.0],
+            [0.1, 0.1, 0.1, 1.0],
+            false,
+        ))
+        .with(Interactable)
+        .with(Selectable::<()>::new(0))
+        .build()
+}
 
-    // flood fill the map until each area has area_size hexes
-    while areas
-        .iter()
-        .any(|(_origin, (size, _player, blocked))| *size < area_size && !blocked)
-    {
-        let mut working_areas = areas
-            .iter()
-            .filter(|(_origin, (size, _player, blocked))| *size < area_size && !blocked)
-            .collect::<Vec<(&Entity, &(u32, Player, bool))>>();
-        working_areas.sort_by(|a, b| a.0.cmp(b.0));
-        let next_area = working_areas.iter().next();
-        // get this area's neighbor hexes
-
-        // check if they are occupied
-        // add one of them to this area
-        // if all are occupied, mark this area as blocked
-    }
+fn create_caption_text(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "caption".to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        -180.0,
+        1.0,
+        400.0,
+        40.0,
+    );
 
-    // color each hex according to which player it represents
-    let mut tints = world.write_storage::<Tint>();
-    // let tint = tints.get_mut(*hex);
-    for (mut tint, player) in (&mut tints, &players).join() {
-        use Player::*;
-        let color = match player {
-            One => Srgb::new(0.306, 0.804, 0.769),
-            Two => Srgb::new(0.780, 0.957, 0.392),
-            Three => Srgb::new(1.0, 0.420, 0.420),
-            Four => Srgb::new(0.769, 0.302, 0.345),
-            Five => Srgb::new(0.333, 0.384, 0.439),
-            Six => Srgb::new(0.286, 0.039, 0.239),
-            Seven => Srgb::new(0.741, 0.082, 0.314),
-            Eight => Srgb::new(0.914, 0.498, 0.008),
-            // Nine => Srgb::new(0.973, 0.792, 0.0),
-            // Ten => Srgb::new(0.541, 0.608, 0.059),
-        };
-        *tint = Tint(color.into());
-
-        println!("tint: {:?}", tint);
-    }
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            32.0,
+        ))
+        .build()
 }
 
-/// the Hexagon component is the basic building block of the game board.
-/// 
-struct Hexagon {
-    x: f32,
-    y: f32,
-    init: Option<InitializedHexagon>,
+fn create_message_text(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "message".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.0,
+        60.0,
+        1.0,
+        600.0,
+        30.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.6, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
 }
 
-struct InitializedHexagon {
-    player: Player,
-    neighbors: [Entity; 6],
-    center: Entity,
+fn game_setup(world: &World) -> GameSetup {
+    world.read_resource::<GameSetup>().clone()
 }
 
+fn create_button(world: &mut World, font: &Handle<FontAsset>, text: &str, x: f32, y: f32) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        x,
+        y,
+        1.0,
+        180.0,
+        50.0,
+    );
 
-impl Component for Hexagon {
-    type Storage = DenseVecStorage<Self>;
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
 }
 
-/// The Player component will enumerate which player any enitity (just hexes for now) belongs to.
-enum Player {
-    One,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
+/// The settings maps are generated from, for the seats set up.
+fn map_settings(world: &World) -> GameSettings {
+    let mut settings = load_settings(world);
+    settings.player_count = Some(game_setup(world).player_count() as u32);
+    settings
 }
 
-impl Component for Player {
-    type Storage = DenseVecStorage<Self>;
+/// Generate the map for `seed`.
+fn generate(world: &mut World, seed: MapSeed) -> Board {
+    mapgen::generate(&map_settings(world), &mut seed.rng()).board
 }
-/// The Area component will store the id of the area to which each hex belongs.
-struct Area {
-    id: usize,
+
+/// A layout that shrinks `board` into the `index`th cell of the preview grid, and the point its
+/// label goes at, under the board.
+fn preview_layout(board: &Board, index: usize) -> (Layout, (f32, f32)) {
+    let rows = (CANDIDATES + PREVIEW_COLUMNS - 1) / PREVIEW_COLUMNS;
+    let column = (index % PREVIEW_COLUMNS) as f32 - (PREVIEW_COLUMNS - 1) as f32 / 2.0;
+    let row = (rows - 1) as f32 / 2.0 - (index / PREVIEW_COLUMNS) as f32;
+    let (cell_width, cell_height) = PREVIEW_CELL;
+    let center = (column * cell_width, row * cell_height + LABEL_HEIGHT / 2.0);
+
+    let board_layout = Layout::BOARD;
+    let width = board.width() as f32 * board_layout.spacing.0;
+    let height = board.height() as f32 * board_layout.spacing.1;
+    let scale = ((cell_width - LABEL_HEIGHT) / width)
+        .min((cell_height - 2.0 * LABEL_HEIGHT) / height)
+        .min(1.0);
+    let layout = Layout {
+        origin: (
+            center.0 - scale * (width / 2.0 - board_layout.origin.0),
+            center.1 - scale * (height / 2.0 - board_layout.origin.1),
+        ),
+        spacing: (
+            scale * board_layout.spacing.0,
+            scale * board_layout.spacing.1,
+        ),
+    };
+    let label = (center.0, center.1 - cell_height / 2.0 + LABEL_HEIGHT / 2.0);
+    (layout, label)
 }
 
-impl Component for Area {
-    type Storage = DenseVecStorage<Self>;
+/// A label kept under a preview by the `BoardLabelSystem`.
+fn create_preview_label(world: &mut World, font: &Handle<FontAsset>, x: f32, y: f32) -> Entity {
+    let transform = UiTransform::new(
+        "preview label".to_string(),
+        Anchor::TopLeft,
+        Anchor::Middle,
+        0.0,
+        0.0,
+        1.0,
+        200.0,
+        30.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .with(BoardLabel { x, y })
+        .build()
 }
@@ -0,0 +1,449 @@
+//! Random map generation: seed territories on an empty board, then grow them into areas.
+
+use rand::{seq::SliceRandom, Rng};
+use std::collections::{HashMap, VecDeque};
+
+use crate::board::{Board, PlayerId, TerritoryId};
+use crate::config::GameSettings;
+use crate::hex::{Hex, Offset};
+use crate::rules;
+
+/// How many times to regenerate a map whose territories are not all connected
+/// before repairing it instead.
+const ATTEMPTS: usize = 4;
+/// How many maps to try, repaired or not, before falling back to one laid out in strips.
+const MAX_ATTEMPTS: usize = 16;
+
+/// The result of generating a map.
+pub struct GeneratedMap {
+    pub board: Board,
+    /// territories that ran out of free neighboring hexes before reaching `area_size`
+    pub blocked: Vec<TerritoryId>,
+}
+
+/// Build a board from the settings, dealing territories out to players in turn.
+///
+/// Every territory on the returned board can reach every other one through its neighbors. A board
+/// with fewer hexes than players is dealt out to as many players as there are hexes.
+pub fn generate<R: Rng>(settings: &GameSettings, rng: &mut R) -> GeneratedMap {
+    let width = settings.width;
+    let height = settings.height;
+    let player_count = settings
+        .player_count
+        .unwrap_or(8)
+        .min(width * height)
+        .max(1);
+    let territory_count = (player_count * 4).min(width * height) as usize;
+
+    let area_size = if settings.area_size == 0 {
+        1
+    } else {
+        settings.area_size
+    } as usize;
+
+    let mut attempt = 0;
+    let mut map = loop {
+        attempt += 1;
+        if attempt > MAX_ATTEMPTS {
+            break GeneratedMap {
+                board: strips(width, height, territory_count, area_size),
+                blocked: vec![],
+            };
+        }
+        // the board is a grid of hexes, which will be combined into
+        // groups of area_size hexes called areas
+        let mut board = Board::new(width, height);
+        for seed in place_seeds(&board, territory_count, area_size, rng) {
+            let territory = board.add_territory(PlayerId(0), 1);
+            board.assign(seed, territory);
+        }
+
+        let blocked = grow_areas(&mut board, area_size, rng);
+        board.rebuild_adjacency();
+
+        let mut map = GeneratedMap { board, blocked };
+        if is_connected(&map.board) {
+            break map;
+        }
+        // a map that can't be repaired is thrown away like the others; the next attempt carries
+        // on from the same rng, so a seed still always gives the same map
+        if attempt >= ATTEMPTS && bridge_all(&mut map.board) {
+            break map;
+        }
+    };
+
+    deal_territories(&mut map.board, player_count as usize, rng);
+    deal_starting_dice(&mut map.board, player_count as usize, rng);
+    map
+}
+
+/// A map is playable only if every territory can be reached from every other one.
+pub fn is_connected(board: &Board) -> bool {
+    board.connected_groups(|_| true).len() <= 1
+}
+
+/// How far from its seed an area of `area_size` hexes reaches when it grows evenly.
+fn area_radius(area_size: usize) -> u32 {
+    let mut radius = 0;
+    // a hexagon of radius r holds 3r(r + 1) + 1 hexes
+    while 3 * radius * (radius + 1) + 1 < area_size as u32 {
+        radius += 1;
+    }
+    radius
+}
+
+/// Pick `count` distinct hexes to start territories from.
+///
+/// Seeds are kept at least one area radius apart so areas have room to grow, and each new seed
+/// lands close enough to an earlier one that their areas will meet. Once there is no room left
+/// that far apart, seeds are allowed a step closer at a time.
+fn place_seeds<R: Rng>(board: &Board, count: usize, area_size: usize, rng: &mut R) -> Vec<Hex> {
+    let radius = area_radius(area_size).max(1);
+    let (mut min_distance, max_distance) = (radius, radius * 2);
+
+    let mut free: Vec<Hex> = board.cells().map(|(hex, _)| hex).collect();
+    free.shuffle(rng);
+
+    let mut seeds: Vec<Hex> = vec![];
+    while seeds.len() < count && !free.is_empty() {
+        let nearest = |hex: &Hex| seeds.iter().map(|seed| seed.distance(*hex)).min();
+        let spaced = |hex: &Hex| nearest(hex).map_or(true, |d| d >= min_distance);
+        let close = |hex: &Hex| nearest(hex).map_or(true, |d| d <= max_distance);
+
+        // prefer hexes that are both spaced out and close, then just spaced out
+        let pick = free
+            .iter()
+            .position(|hex| spaced(hex) && close(hex))
+            .or_else(|| free.iter().position(|hex| spaced(hex)));
+        match pick {
+            Some(pick) => {
+                seeds.push(free.swap_remove(pick));
+            }
+            // every free hex is at least one step from every seed, so this stops at 1
+            None => min_distance -= 1,
+        }
+    }
+    seeds
+}
+
+/// Flood fill the map until each area has area_size hexes.
+///
+/// Areas take turns claiming one free hex next to them, so they grow at the same pace.
+/// An area with no free hex left to claim is blocked and stops growing; blocked areas are returned.
+fn grow_areas<R: Rng>(board: &mut Board, area_size: usize, rng: &mut R) -> Vec<TerritoryId> {
+    let mut blocked = vec![false; board.territory_count()];
+    let growing = |board: &Board, blocked: &[bool]| -> Vec<TerritoryId> {
+        board
+            .territories()
+            .filter(|area| area.size() < area_size && !blocked[area.id.0])
+            .map(|area| area.id)
+            .collect()
+    };
+
+    let mut working_areas = growing(board, &blocked);
+    while !working_areas.is_empty() {
+        for &area in &working_areas {
+            // get this area's neighbor hexes, and keep the ones that are unoccupied
+            let mut free: Vec<Hex> = board
+                .territory(area)
+                .hexes()
+                .iter()
+                .flat_map(|&hex| board.neighbor_cells(hex))
+                .filter(|&hex| board.territory_at(hex).is_none())
+                .collect();
+            free.sort();
+            free.dedup();
+
+            // add one of them to this area, or mark it blocked if there are none
+            match free.choose(rng) {
+                Some(&hex) => board.assign(hex, area),
+                None => blocked[area.0] = true,
+            }
+        }
+        working_areas = growing(board, &blocked);
+    }
+
+    blocked
+        .iter()
+        .enumerate()
+        .filter(|(_, &blocked)| blocked)
+        .map(|(id, _)| TerritoryId(id))
+        .collect()
+}
+
+/// A map that is always connected, if dull: territories cut one after another from a path that
+/// snakes down one column and up the next, so each touches the one cut before it.
+fn strips(width: u32, height: u32, territory_count: usize, area_size: usize) -> Board {
+    let mut board = Board::new(width, height);
+    let path = (0..width as i32).flat_map(|col| {
+        (0..height as i32).map(move |step| {
+            let row = if col % 2 == 0 {
+                step
+            } else {
+                height as i32 - 1 - step
+            };
+            Hex::from(Offset::new(col, row))
+        })
+    });
+    let mut territory = None;
+    for (i, hex) in path.enumerate().take(territory_count * area_size) {
+        if i % area_size == 0 {
+            territory = Some(board.add_territory(PlayerId(0), 1));
+        }
+        if let Some(territory) = territory {
+            board.assign(hex, territory);
+        }
+    }
+    board.rebuild_adjacency();
+    board
+}
+
+/// Bridge islands until every territory is connected. Returns false if that can't be done.
+fn bridge_all(board: &mut Board) -> bool {
+    while !is_connected(board) {
+        if !bridge_islands(board) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Connect the smallest group of territories to the rest of the map, by extending one of its
+/// territories along the shortest run of free hexes to a territory outside the group.
+/// Returns false if there is no such run.
+fn bridge_islands(board: &mut Board) -> bool {
+    let groups = board.connected_groups(|_| true);
+    let island = match groups.last() {
+        Some(island) if groups.len() > 1 => island,
+        _ => return true,
+    };
+    let in_island = |territory: TerritoryId| island.binary_search(&territory).is_ok();
+
+    // breadth first search outward from the island, through free hexes only
+    let mut came_from: HashMap<Hex, Option<Hex>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for &territory in island {
+        for &hex in board.territory(territory).hexes() {
+            came_from.insert(hex, None);
+            queue.push_back(hex);
+        }
+    }
+    while let Some(hex) = queue.pop_front() {
+        let neighbors: Vec<Hex> = board.neighbor_cells(hex).collect();
+        for neighbor in neighbors {
+            if came_from.contains_key(&neighbor) {
+                continue;
+            }
+            match board.territory_at(neighbor) {
+                Some(territory) if !in_island(territory) => {
+                    // walk back to the island, then give the path to the territory it started from
+                    let mut path = vec![];
+                    let mut step = hex;
+                    while let Some(&Some(previous)) = came_from.get(&step) {
+                        path.push(step);
+                        step = previous;
+                    }
+                    let owner = board
+                        .territory_at(step)
+                        .expect("bridge search started outside the island");
+                    for hex in path {
+                        board.assign(hex, owner);
+                    }
+                    board.rebuild_adjacency();
+                    return true;
+                }
+                Some(_) => {}
+                None => {
+                    came_from.insert(neighbor, Some(hex));
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Hand the territories out to players in a random order, so everyone gets the same number
+/// (give or take one).
+fn deal_territories<R: Rng>(board: &mut Board, player_count: usize, rng: &mut R) {
+    let mut territories: Vec<TerritoryId> = board.territories().map(|t| t.id).collect();
+    territories.shuffle(rng);
+    for (i, territory) in territories.into_iter().enumerate() {
+        board.set_owner(territory, PlayerId(i % player_count));
+    }
+}
+
+/// On top of the one die every territory starts with, each player gets the same number of extra
+/// dice, about one more per territory, scattered the same way as end of turn reinforcements.
+fn deal_starting_dice<R: Rng>(board: &mut Board, player_count: usize, rng: &mut R) {
+    let extra = (board.territory_count() / player_count) as u32;
+    for player in 0..player_count {
+        rules::place_dice(board, PlayerId(player), extra, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::MapSeed;
+
+    use std::collections::HashSet;
+
+    fn settings(player_count: u32) -> GameSettings {
+        GameSettings {
+            width: 16,
+            height: 16,
+            area_size: 6,
+            player_count: Some(player_count),
+            ..GameSettings::default()
+        }
+    }
+
+    /// The smallest distance between any two of `seeds`.
+    fn closest(seeds: &[Hex]) -> u32 {
+        seeds
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| seeds[i + 1..].iter().map(move |b| a.distance(*b)))
+            .min()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn seeds_keep_their_distance_when_there_is_room() {
+        let board = Board::new(16, 16);
+        for seed in 0..10 {
+            // territories of 19 hexes reach 2 steps from their seed
+            let seeds = place_seeds(&board, 12, 19, &mut MapSeed(seed).rng());
+            assert_eq!(seeds.len(), 12);
+            assert!(closest(&seeds) >= 2, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn crowded_seeds_move_closer() {
+        let board = Board::new(6, 6);
+        for seed in 0..10 {
+            // territories of 37 hexes want seeds 3 apart, and 12 of those don't fit on 6 x 6
+            let seeds = place_seeds(&board, 12, 37, &mut MapSeed(seed).rng());
+            assert_eq!(seeds.len(), 12);
+            assert!(closest(&seeds) < 3);
+
+            let everywhere = place_seeds(&board, 36, 37, &mut MapSeed(seed).rng());
+            let distinct: HashSet<Hex> = everywhere.iter().cloned().collect();
+            assert_eq!(distinct.len(), 36);
+        }
+    }
+
+    #[test]
+    fn every_player_starts_with_the_same_extra_dice() {
+        for seed in 0..10 {
+            let board = generate(&settings(3), &mut MapSeed(seed).rng()).board;
+            let extra = (board.territory_count() / 3) as u32;
+            for player in 0..3 {
+                let owned: Vec<u32> = board
+                    .territories_of(PlayerId(player))
+                    .map(|territory| territory.dice)
+                    .collect();
+                let dice: u32 = owned.iter().sum();
+                assert_eq!(dice, owned.len() as u32 + extra, "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn starting_dice_are_dealt_the_same_from_the_same_seed() {
+        // six one-hex territories, two for each of three players
+        let mut board = Board::new(6, 1);
+        for col in 0..6 {
+            let territory = board.add_territory(PlayerId(col as usize % 3), 1);
+            board.assign(Offset::new(col, 0).into(), territory);
+        }
+        let deal = |seed: u64| {
+            let mut board = board.clone();
+            deal_starting_dice(&mut board, 3, &mut MapSeed(seed).rng());
+            board
+        };
+        for seed in 0..20 {
+            let dealt = deal(seed);
+            for player in 0..3 {
+                let dice: u32 = dealt
+                    .territories_of(PlayerId(player))
+                    .map(|territory| territory.dice)
+                    .sum();
+                assert_eq!(dice, 4);
+            }
+            assert_eq!(dealt, deal(seed));
+        }
+    }
+
+    #[test]
+    fn tiny_boards_go_to_as_many_players_as_they_hold() {
+        let settings = GameSettings {
+            width: 2,
+            height: 2,
+            area_size: 1,
+            ..settings(8)
+        };
+        for seed in 0..5 {
+            let board = generate(&settings, &mut MapSeed(seed).rng()).board;
+            assert_eq!(board.territory_count(), 4);
+            for player in 0..4 {
+                // one territory each, with one extra die
+                let dice: Vec<u32> = board
+                    .territories_of(PlayerId(player))
+                    .map(|territory| territory.dice)
+                    .collect();
+                assert_eq!(dice, vec![2], "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn odd_settings_still_make_a_connected_map() {
+        // width, height and area size
+        let odd = [
+            (1, 1, 1),
+            (1, 40, 3),
+            (40, 2, 5),
+            (5, 5, 0),
+            (0, 0, 4),
+            (8, 8, 30),
+        ];
+        for &(width, height, area_size) in odd.iter() {
+            let settings = GameSettings {
+                width,
+                height,
+                area_size,
+                ..settings(8)
+            };
+            for seed in 0..5 {
+                let board = generate(&settings, &mut MapSeed(seed).rng()).board;
+                assert!(is_connected(&board));
+            }
+        }
+    }
+
+    #[test]
+    fn strips_are_connected() {
+        // width, height, territories and area size
+        let sizes = [(1, 1, 1, 1), (5, 3, 4, 4), (16, 16, 32, 6)];
+        for &(width, height, count, area_size) in sizes.iter() {
+            let board = strips(width, height, count, area_size);
+            assert_eq!(board.territory_count(), count);
+            assert!(is_connected(&board));
+            for territory in board.territories() {
+                assert!(territory.size() <= area_size);
+            }
+        }
+    }
+
+    #[test]
+    fn the_same_seed_deals_the_same_map() {
+        for seed in 0..5 {
+            let a = generate(&settings(4), &mut MapSeed(seed).rng()).board;
+            let b = generate(&settings(4), &mut MapSeed(seed).rng()).board;
+            assert_eq!(a, b);
+        }
+    }
+}
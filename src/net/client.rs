@@ -0,0 +1,85 @@
+//! The client's end: one connection to the host.
+
+use super::protocol::{read_message, write_message, ClientMessage, HostMessage, VERSION};
+use super::NetError;
+
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for a host to answer before giving up on it.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A connection to a host. What the host sends is read on a thread of its own, and picked up by
+/// the game with `poll`.
+pub struct Client {
+    /// the address the host was reached at, and the name this client goes by, to join again with
+    address: String,
+    name: String,
+    stream: TcpStream,
+    messages: Mutex<Receiver<Result<HostMessage, NetError>>>,
+}
+
+impl Client {
+    /// Connect to the host at `address` and say hello as `name`.
+    pub fn connect(address: &str, name: &str) -> Result<Client, NetError> {
+        let host = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| NetError::Format(format!("no address found for {}", address)))?;
+        let stream = TcpStream::connect_timeout(&host, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        let hello = ClientMessage::Hello {
+            version: VERSION,
+            name: name.to_string(),
+        };
+        write_message(&mut &stream, &hello)?;
+
+        let mut reader = stream.try_clone()?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || loop {
+            let message = read_message(&mut reader);
+            let done = message.is_err();
+            if sender.send(message).is_err() || done {
+                break;
+            }
+        });
+
+        Ok(Client {
+            address: address.to_string(),
+            name: name.to_string(),
+            stream,
+            messages: Mutex::new(receiver),
+        })
+    }
+
+    /// Everything the host sent since the last poll, in order. Once the connection is lost, the
+    /// last entry says why.
+    pub fn poll(&self) -> Vec<Result<HostMessage, NetError>> {
+        self.messages
+            .lock()
+            .expect("client messages poisoned")
+            .try_iter()
+            .collect()
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn send(&self, message: &ClientMessage) -> Result<(), NetError> {
+        write_message(&mut &self.stream, message)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
@@ -0,0 +1,189 @@
+//! The host's end: listens for clients, and passes what they say on to the game.
+
+use serde::{Deserialize, Serialize};
+
+use super::protocol::{read_message, write_message, ClientMessage, HostMessage, VERSION};
+use super::NetError;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long the listener sleeps when nobody is knocking.
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+/// A connected client, numbered in the order they connected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct PeerId(pub usize);
+
+/// Something that happened on one of the host's connections.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostEvent {
+    /// a client said hello in the right version, and goes by the name
+    Joined(PeerId, String),
+    /// a client said something after their hello
+    Message(PeerId, ClientMessage),
+    /// a client hung up, or was cut off
+    Left(PeerId),
+}
+
+/// Accepts clients on a thread of its own, and one more thread per client reads what they send.
+/// The game picks up what they said with `poll`. Hanging up on everyone happens when it's dropped.
+pub struct Host {
+    address: SocketAddr,
+    peers: Arc<Mutex<HashMap<PeerId, TcpStream>>>,
+    events: Mutex<Receiver<HostEvent>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Host {
+    /// Listen for clients at `address`, like "0.0.0.0:7077". Port 0 picks any free port.
+    pub fn listen(address: &str) -> Result<Host, NetError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let peers = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let accepted = peers.clone();
+        let stop = closed.clone();
+        thread::spawn(move || {
+            let mut next = 0;
+            while !stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let peer = PeerId(next);
+                        next += 1;
+                        // a peer lost straight away never joined, so there is no one to tell
+                        let _ = spawn_peer(peer, stream, &accepted, &sender);
+                    }
+                    Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_POLL);
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+
+        Ok(Host {
+            address,
+            peers,
+            events: Mutex::new(receiver),
+            closed,
+        })
+    }
+
+    /// Where clients can reach this host.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Everything that happened since the last poll, in order.
+    pub fn poll(&self) -> Vec<HostEvent> {
+        self.events
+            .lock()
+            .expect("host events poisoned")
+            .try_iter()
+            .collect()
+    }
+
+    /// Send `message` to `peer`. Sending to someone who left does nothing.
+    pub fn send(&self, peer: PeerId, message: &HostMessage) {
+        let peers = self.peers.lock().expect("host peers poisoned");
+        if let Some(mut stream) = peers.get(&peer) {
+            // a peer that can't be written to is gone, which its reader reports as it leaving
+            let _ = write_message(&mut stream, message);
+        }
+    }
+
+    /// Send `message` to every client.
+    pub fn broadcast(&self, message: &HostMessage) {
+        let peers = self.peers.lock().expect("host peers poisoned");
+        for mut stream in peers.values() {
+            let _ = write_message(&mut stream, message);
+        }
+    }
+
+    /// Tell `peer` why they can't play, and hang up on them.
+    pub fn refuse(&self, peer: PeerId, reason: &str) {
+        self.send(peer, &HostMessage::Refused(reason.to_string()));
+        if let Some(stream) = self
+            .peers
+            .lock()
+            .expect("host peers poisoned")
+            .remove(&peer)
+        {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for Host {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        if let Ok(mut peers) = self.peers.lock() {
+            for (_, stream) in peers.drain() {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+/// Start reading from a newly connected client. It only counts as joined once it has said hello
+/// in the right version; anything else is refused.
+fn spawn_peer(
+    peer: PeerId,
+    stream: TcpStream,
+    peers: &Arc<Mutex<HashMap<PeerId, TcpStream>>>,
+    events: &Sender<HostEvent>,
+) -> Result<(), NetError> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    let mut reader = stream.try_clone()?;
+    let peers = peers.clone();
+    let events = events.clone();
+    thread::spawn(move || {
+        let mut writer = stream;
+        let name = match read_message(&mut reader) {
+            Ok(ClientMessage::Hello { version, name }) if version == VERSION => name,
+            Ok(ClientMessage::Hello { version, .. }) => {
+                let reason = NetError::Version(version).to_string();
+                let _ = write_message(&mut writer, &HostMessage::Refused(reason));
+                return;
+            }
+            Ok(_) | Err(_) => {
+                let reason = "expected a hello from a hexadie client".to_string();
+                let _ = write_message(&mut writer, &HostMessage::Refused(reason));
+                return;
+            }
+        };
+        peers
+            .lock()
+            .expect("host peers poisoned")
+            .insert(peer, writer);
+        if events.send(HostEvent::Joined(peer, name)).is_err() {
+            return;
+        }
+
+        loop {
+            match read_message(&mut reader) {
+                Ok(ClientMessage::Hello { .. }) => {}
+                Ok(message) => {
+                    if events.send(HostEvent::Message(peer, message)).is_err() {
+                        return;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        peers.lock().expect("host peers poisoned").remove(&peer);
+        let _ = events.send(HostEvent::Left(peer));
+    });
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! The table a network game is set up at: who sits where, in which colors, and on what map.
+//!
+//! The host keeps the lobby and sends it to every client whenever it changes, so everyone sees the
+//! same table. The host sits in the first seat. Clients sit in human seats, and a human seat
+//! nobody sits in is open for the next client to join.
+
+use serde::{Deserialize, Serialize};
+
+use super::PeerId;
+use crate::board::PlayerId;
+use crate::config::GameSettings;
+use crate::players::{Controller, Palette, Players};
+use crate::setup::{Difficulty, GameSetup, Seat};
+
+use std::collections::HashMap;
+
+/// The smallest and largest maps the host can pick, in hexes across.
+pub const MIN_SIZE: u32 = 12;
+pub const MAX_SIZE: u32 = 64;
+/// How much one click makes the map bigger or smaller.
+pub const SIZE_STEP: u32 = 4;
+/// The smallest and largest territories the host can pick, in hexes.
+pub const MIN_AREA: u32 = 3;
+pub const MAX_AREA: u32 = 16;
+
+/// A client sitting at the table.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Guest {
+    pub peer: PeerId,
+    /// whether they are happy for the game to start
+    pub ready: bool,
+}
+
+/// The players of a game started from the lobby.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Seating {
+    pub setup: GameSetup,
+    pub players: Players,
+    /// the player each client plays
+    pub seated: HashMap<PeerId, PlayerId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Lobby {
+    /// the settings the map will be generated from
+    pub settings: GameSettings,
+    /// the seats, with their names and colors
+    pub setup: GameSetup,
+    /// the client sitting in each seat, if any
+    pub guests: Vec<Option<Guest>>,
+}
+
+impl Lobby {
+    /// The host in the first seat, and every other seat open.
+    pub fn new(settings: GameSettings, max_seats: usize) -> Lobby {
+        let setup = GameSetup::from_settings(&settings, max_seats);
+        let mut lobby = Lobby {
+            settings,
+            setup,
+            guests: vec![],
+        };
+        let count = lobby.setup.seats.len();
+        for seat in lobby.setup.seats.iter_mut().skip(1) {
+            *seat = Seat::Human;
+        }
+        lobby.set_seat_count(count);
+        lobby
+    }
+
+    /// Whether `seat` is waiting for a client to join.
+    pub fn is_open(&self, seat: usize) -> bool {
+        seat > 0 && self.setup.seats.get(seat) == Some(&Seat::Human) && self.guest(seat).is_none()
+    }
+
+    pub fn guest(&self, seat: usize) -> Option<&Guest> {
+        self.guests.get(seat).and_then(Option::as_ref)
+    }
+
+    /// The seat `peer` sits in.
+    pub fn seat_of(&self, peer: PeerId) -> Option<usize> {
+        self.guests
+            .iter()
+            .position(|guest| guest.as_ref().map(|g| g.peer) == Some(peer))
+    }
+
+    /// The name shown for `seat`: the name typed in for it, or else the name of its color.
+    pub fn seat_name(&self, seat: usize, palette: &Palette) -> String {
+        let color = palette.players.get(self.setup.color(seat));
+        match (self.setup.name(seat), color) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(entry)) => entry.name.clone(),
+            (None, None) => format!("seat {}", seat + 1),
+        }
+    }
+
+    /// Seat `peer` in the first open seat, under `name`. Returns the seat, or why they can't join.
+    /// Nobody may take a name already shown for another seat, typed in or not.
+    pub fn join(&mut self, peer: PeerId, name: &str, palette: &Palette) -> Result<usize, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("a name is needed to join".to_string());
+        }
+        let seat = match (0..self.setup.seats.len()).find(|&seat| self.is_open(seat)) {
+            Some(seat) => seat,
+            None => return Err("the game is full".to_string()),
+        };
+        let taken = (0..self.setup.seats.len())
+            .filter(|&other| other != seat)
+            .any(|other| self.seat_name(other, palette).eq_ignore_ascii_case(name));
+        if taken {
+            return Err(format!("someone called {} is already here", name));
+        }
+        self.guests[seat] = Some(Guest { peer, ready: false });
+        self.setup.set_name(seat, name);
+        Ok(seat)
+    }
+
+    /// Take `peer` away from the table, leaving their seat open. Returns the seat they had.
+    pub fn leave(&mut self, peer: PeerId) -> Option<usize> {
+        let seat = self.seat_of(peer)?;
+        self.guests[seat] = None;
+        self.setup.set_name(seat, "");
+        Some(seat)
+    }
+
+    pub fn set_ready(&mut self, peer: PeerId, ready: bool) {
+        if let Some(seat) = self.seat_of(peer) {
+            self.guests[seat] = Some(Guest { peer, ready });
+        }
+    }
+
+    /// Add or remove seats at the end of the table. New seats are open; returns the clients who
+    /// were sitting in the seats taken away.
+    pub fn set_seat_count(&mut self, count: usize) -> Vec<PeerId> {
+        let old = self.setup.seats.len();
+        self.setup.set_seat_count(count);
+        let count = self.setup.seats.len();
+        for seat in self.setup.seats.iter_mut().skip(old) {
+            *seat = Seat::Human;
+        }
+        self.guests.resize(old.max(count), None);
+        let removed = self.guests.split_off(count);
+        self.settings.player_count = Some(count as u32);
+        removed
+            .into_iter()
+            .flatten()
+            .map(|guest| guest.peer)
+            .collect()
+    }
+
+    /// Change who sits in a seat nobody joined: open, an AI of each difficulty, or nobody.
+    pub fn cycle_seat(&mut self, seat: usize) {
+        if seat > 0 && seat < self.setup.seats.len() && self.guest(seat).is_none() {
+            self.setup.seats[seat] = self.setup.seats[seat].next();
+        }
+    }
+
+    /// Put an AI in every open seat.
+    pub fn fill_with_ai(&mut self) {
+        for seat in 0..self.setup.seats.len() {
+            if self.is_open(seat) {
+                self.setup.seats[seat] = Seat::Ai(Difficulty::Normal);
+            }
+        }
+    }
+
+    /// Make the map `steps` steps bigger, or smaller for negative steps.
+    pub fn resize_map(&mut self, steps: i32) {
+        let resize = |size: u32| clamp(size as i32 + steps * SIZE_STEP as i32, MIN_SIZE, MAX_SIZE);
+        self.settings.width = resize(self.settings.width);
+        self.settings.height = resize(self.settings.height);
+    }
+
+    /// Make territories `change` hexes bigger, or smaller when negative.
+    pub fn resize_areas(&mut self, change: i32) {
+        let area = self.settings.area_size as i32 + change;
+        self.settings.area_size = clamp(area, MIN_AREA, MAX_AREA);
+    }
+
+    /// Whether every client has said they are ready.
+    pub fn is_ready(&self) -> bool {
+        self.guests.iter().flatten().all(|guest| guest.ready)
+    }
+
+    /// Who plays the game, with open seats left out. Returns why the game can't start yet instead,
+    /// if it can't.
+    pub fn start(&self, palette: &Palette) -> Result<Seating, String> {
+        if !self.is_ready() {
+            return Err("not everyone is ready".to_string());
+        }
+        let mut setup = self.setup.clone();
+        for seat in 0..setup.seats.len() {
+            if self.is_open(seat) {
+                setup.seats[seat] = Seat::Empty;
+            }
+        }
+        if !setup.is_playable() {
+            return Err("a game needs at least two players".to_string());
+        }
+
+        let mut players = Players::from_setup(&setup, palette);
+        let mut seated = HashMap::new();
+        let occupied = setup
+            .seats
+            .iter()
+            .enumerate()
+            .filter(|(_, &s)| s != Seat::Empty);
+        for (player, (seat, _)) in occupied.enumerate() {
+            if let Some(guest) = self.guest(seat) {
+                players.0[player].controller = Controller::Remote;
+                seated.insert(guest.peer, PlayerId(player));
+            }
+        }
+        Ok(Seating {
+            setup,
+            players,
+            seated,
+        })
+    }
+}
+
+fn clamp(value: i32, min: u32, max: u32) -> u32 {
+    if value < min as i32 {
+        min
+    } else if value > max as i32 {
+        max
+    } else {
+        value as u32
+    }
+}
@@ -0,0 +1,483 @@
+//! Network play over TCP: one instance hosts, and others join it by address.
+//!
+//! Players meet in a lobby first, which the host keeps and sends to everyone whenever it changes.
+//! The host runs the rules. When a game starts it sends each client a replay of it so far and
+//! which player they are, and from then on every action it plays, in order. A client who drops
+//! out mid-game gets the same again when they join under the same name. Maps and dice come
+//! from the seed, so clients playing those actions through their own rules stay in step without
+//! being sent any dice. Clients only ever send what they would like to do, and the host decides.
+//!
+//! This module only needs the standard library, so hosts and clients can be tested without a
+//! window.
+
+mod client;
+mod host;
+mod lobby;
+mod protocol;
+
+pub use client::Client;
+pub use host::{Host, HostEvent, PeerId};
+pub use lobby::{Guest, Lobby, Seating};
+pub use protocol::{read_message, write_message, ClientMessage, HostMessage, VERSION};
+
+use crate::board::PlayerId;
+use crate::game::{Action, Game};
+use crate::players::{Controller, Players};
+use crate::rules::{self, Attack};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+/// The port hosts listen on, and clients connect to, unless told otherwise.
+pub const DEFAULT_PORT: u16 = 7077;
+
+/// How the game was asked to start a network game, from the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Launch {
+    /// listen for players joining at this address
+    Host(String),
+    /// join the game hosted at this address, going by this name
+    Join(String, String),
+}
+
+/// The connection a network game is played over, inserted as a resource while there is one.
+pub enum Network {
+    Host {
+        host: Host,
+        /// the player each client plays, once the game has started
+        seated: HashMap<PeerId, PlayerId>,
+    },
+    Client {
+        client: Client,
+    },
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    /// the other side sent something that isn't a message
+    Format(String),
+    /// the other side speaks another version of the protocol
+    Version(u32),
+    /// the host turned us away
+    Refused(String),
+    /// the other side hung up
+    Closed,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(error) => write!(f, "{}", error),
+            NetError::Format(error) => write!(f, "garbled message: {}", error),
+            NetError::Version(version) => write!(
+                f,
+                "another version of the game (protocol version {}, expected {})",
+                version, VERSION
+            ),
+            NetError::Refused(reason) => write!(f, "refused by the host: {}", reason),
+            NetError::Closed => write!(f, "the connection was closed"),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(error: io::Error) -> NetError {
+        NetError::Io(error)
+    }
+}
+
+/// Add the default port to an address that doesn't have one.
+pub fn with_default_port(address: &str) -> String {
+    if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+/// The players as a client sees them: their own is played at their screen, and everyone else's
+/// comes from the host.
+pub fn client_view(players: &Players, you: PlayerId) -> Players {
+    let mut players = players.clone();
+    for player in players.0.iter_mut() {
+        player.controller = if player.id == you {
+            Controller::Human
+        } else {
+            Controller::Remote
+        };
+    }
+    players
+}
+
+/// Whether the host should play what `player`'s client asked for, in `game` as it stands. Clients
+/// may send anything, so territories are checked to be on the board before the rules look at them.
+pub fn check_intent(game: &Game, player: PlayerId, action: Action) -> Result<(), String> {
+    if game.current_player() != player {
+        return Err("it isn't your turn".to_string());
+    }
+    match action {
+        Action::Attack(Attack { from, to }) => {
+            let board = game.board();
+            if from.0 >= board.territory_count() || to.0 >= board.territory_count() {
+                return Err("there is no such territory".to_string());
+            }
+            rules::check_attack(board, player, Attack { from, to })
+                .map_err(|error| error.to_string())
+        }
+        Action::EndTurn => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lobby::{MAX_AREA, MAX_SIZE, MIN_AREA, MIN_SIZE};
+    use super::*;
+    use crate::ai::{HeuristicAi, Strategy};
+    use crate::board::TerritoryId;
+    use crate::config::GameSettings;
+    use crate::mapgen;
+    use crate::players::{Palette, PaletteEntry};
+    use crate::replay::Replay;
+    use crate::seed::MapSeed;
+    use crate::setup::{Difficulty, Seat};
+
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Poll until something turns up, or fail after a few seconds.
+    fn wait_for<T>(mut poll: impl FnMut() -> Vec<T>) -> Vec<T> {
+        let start = Instant::now();
+        loop {
+            let found = poll();
+            if !found.is_empty() {
+                return found;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "nothing arrived");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let attack = Action::Attack(Attack {
+            from: TerritoryId(3),
+            to: TerritoryId(14),
+        });
+        let mut wire = vec![];
+        write_message(&mut wire, &ClientMessage::Intent(attack)).unwrap();
+        write_message(&mut wire, &ClientMessage::Intent(Action::EndTurn)).unwrap();
+
+        let mut reader = wire.as_slice();
+        let first: ClientMessage = read_message(&mut reader).unwrap();
+        let second: ClientMessage = read_message(&mut reader).unwrap();
+        assert_eq!(first, ClientMessage::Intent(attack));
+        assert_eq!(second, ClientMessage::Intent(Action::EndTurn));
+        match read_message::<_, ClientMessage>(&mut reader) {
+            Err(NetError::Closed) => {}
+            other => panic!("expected the end, got {:?}", other),
+        }
+
+        // a length far past anything sensible is refused rather than read
+        let mut reader: &[u8] = &[0xff, 0xff, 0xff, 0xff, b'('];
+        match read_message::<_, ClientMessage>(&mut reader) {
+            Err(NetError::Format(_)) => {}
+            other => panic!("expected a format error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn host_and_client_talk() {
+        let host = Host::listen("127.0.0.1:0").unwrap();
+        let client = Client::connect(&host.address().to_string(), "ada").unwrap();
+
+        let peer = match wait_for(|| host.poll()).as_slice() {
+            [HostEvent::Joined(peer, name)] => {
+                assert_eq!(name, "ada");
+                *peer
+            }
+            other => panic!("expected a join, got {:?}", other),
+        };
+
+        client
+            .send(&ClientMessage::Intent(Action::EndTurn))
+            .unwrap();
+        assert_eq!(
+            wait_for(|| host.poll()),
+            vec![HostEvent::Message(
+                peer,
+                ClientMessage::Intent(Action::EndTurn)
+            )]
+        );
+
+        host.broadcast(&HostMessage::Resolved(Action::EndTurn));
+        match wait_for(|| client.poll()).as_slice() {
+            [Ok(HostMessage::Resolved(Action::EndTurn))] => {}
+            other => panic!("expected the action back, got {:?}", other),
+        }
+
+        drop(client);
+        assert_eq!(wait_for(|| host.poll()), vec![HostEvent::Left(peer)]);
+    }
+
+    #[test]
+    fn clients_stay_in_step() {
+        let seed = MapSeed(0xc0ffee);
+        let settings = GameSettings {
+            width: 16,
+            height: 16,
+            area_size: 6,
+            player_count: Some(3),
+            ..GameSettings::default()
+        };
+        let mut lobby = Lobby::new(settings, 8);
+
+        let host = Host::listen("127.0.0.1:0").unwrap();
+        let client = Client::connect(&host.address().to_string(), "ada").unwrap();
+        let peer = match wait_for(|| host.poll()).as_slice() {
+            [HostEvent::Joined(peer, name)] => {
+                assert_eq!(lobby.join(*peer, name, &Palette::default()), Ok(1));
+                *peer
+            }
+            other => panic!("expected a join, got {:?}", other),
+        };
+        lobby.cycle_seat(2);
+        lobby.set_ready(peer, true);
+        let table = HostMessage::Lobby {
+            lobby: Box::new(lobby.clone()),
+            seat: 1,
+        };
+        host.send(peer, &table);
+        match wait_for(|| client.poll()).remove(0) {
+            Ok(HostMessage::Lobby { lobby: seen, seat }) => {
+                assert_eq!(seat, 1);
+                assert_eq!(seen.setup, lobby.setup);
+                assert_eq!(seen.guests, lobby.guests);
+            }
+            other => panic!("expected the lobby, got {:?}", other.map(|_| ())),
+        }
+
+        let Seating {
+            setup,
+            players,
+            seated,
+        } = lobby.start(&Palette::default()).unwrap();
+        assert_eq!(seated.len(), 1);
+        let you = seated[&peer];
+        let board = mapgen::generate(&lobby.settings, &mut seed.rng()).board;
+        let mut game = Game::new(board, setup.turn_order(), seed.game_rng());
+        let replay = Replay::record(seed, lobby.settings.clone(), setup, players, &game);
+        host.send(
+            peer,
+            &HostMessage::Start {
+                replay: Box::new(replay),
+                you,
+            },
+        );
+
+        // the client sets up from what it was sent
+        let mut mirror = match wait_for(|| client.poll()).remove(0) {
+            Ok(HostMessage::Start { replay, you: me }) => {
+                assert_eq!(me, you);
+                replay.game_at(replay.actions.len())
+            }
+            other => panic!("expected the start, got {:?}", other.map(|_| ())),
+        };
+
+        // the host plays, and the client follows along
+        for _ in 0..80 {
+            if game.winner().is_some() {
+                break;
+            }
+            let action = HeuristicAi::new(0.6).next_action(game.board(), game.current_player());
+            game.apply(action).unwrap();
+            host.broadcast(&HostMessage::Resolved(action));
+        }
+        let mut received = 0;
+        while received < game.history().len() {
+            for message in wait_for(|| client.poll()) {
+                match message {
+                    Ok(HostMessage::Resolved(action)) => mirror.apply(action).unwrap(),
+                    other => panic!("expected an action, got {:?}", other.map(|_| ())),
+                };
+                received += 1;
+            }
+        }
+        assert_eq!(mirror, game);
+    }
+
+    #[test]
+    fn rejects_intents_off_the_board() {
+        let seed = MapSeed(0xbad1d);
+        let settings = GameSettings {
+            width: 8,
+            height: 8,
+            area_size: 4,
+            player_count: Some(2),
+            ..GameSettings::default()
+        };
+        let board = mapgen::generate(&settings, &mut seed.rng()).board;
+        let game = Game::new(board, vec![PlayerId(0), PlayerId(1)], seed.game_rng());
+        let player = game.current_player();
+        let outside = TerritoryId(game.board().territory_count());
+
+        let host = Host::listen("127.0.0.1:0").unwrap();
+        let client = Client::connect(&host.address().to_string(), "ada").unwrap();
+        let peer = match wait_for(|| host.poll()).as_slice() {
+            [HostEvent::Joined(peer, _)] => *peer,
+            other => panic!("expected a join, got {:?}", other),
+        };
+        let attacks = [
+            Attack {
+                from: outside,
+                to: TerritoryId(0),
+            },
+            Attack {
+                from: TerritoryId(0),
+                to: TerritoryId(outside.0 + 1),
+            },
+        ];
+        for &attack in attacks.iter() {
+            client
+                .send(&ClientMessage::Intent(Action::Attack(attack)))
+                .unwrap();
+            // the host checks the intent as it would mid-game, and turns it down
+            match wait_for(|| host.poll()).remove(0) {
+                HostEvent::Message(from, ClientMessage::Intent(action)) => {
+                    assert_eq!(from, peer);
+                    let reason = check_intent(&game, player, action).unwrap_err();
+                    host.send(peer, &HostMessage::Rejected(reason));
+                }
+                other => panic!("expected an intent, got {:?}", other),
+            }
+            match wait_for(|| client.poll()).remove(0) {
+                Ok(HostMessage::Rejected(reason)) => {
+                    assert_eq!(reason, "there is no such territory")
+                }
+                other => panic!("expected a rejection, got {:?}", other.map(|_| ())),
+            }
+        }
+        assert_eq!(check_intent(&game, player, Action::EndTurn), Ok(()));
+    }
+
+    #[test]
+    fn refuses_other_versions() {
+        let host = Host::listen("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(host.address()).unwrap();
+        let hello = ClientMessage::Hello {
+            version: VERSION + 1,
+            name: "from the future".to_string(),
+        };
+        write_message(&mut stream, &hello).unwrap();
+        match read_message::<_, HostMessage>(&mut stream) {
+            Ok(HostMessage::Refused(_)) => {}
+            other => panic!("expected to be refused, got {:?}", other.map(|_| ())),
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert!(host.poll().is_empty());
+    }
+
+    #[test]
+    fn lobby_seats_clients_in_open_seats() {
+        let settings = GameSettings {
+            player_count: Some(4),
+            ..GameSettings::default()
+        };
+        let mut lobby = Lobby::new(settings, 6);
+        assert_eq!(lobby.setup.seats, vec![Seat::Human; 4]);
+        let palette = Palette {
+            players: ["red", "blue", "green", "gold", "pink", "gray"]
+                .iter()
+                .map(|&name| PaletteEntry {
+                    name: name.to_string(),
+                    color: (0.5, 0.5, 0.5),
+                    team: None,
+                })
+                .collect(),
+        };
+
+        assert_eq!(lobby.join(PeerId(4), " ada ", &palette), Ok(1));
+        assert!(lobby.join(PeerId(5), "ADA", &palette).is_err());
+        assert!(lobby.join(PeerId(5), " ", &palette).is_err());
+        // nor can anyone take the name shown for another seat's color
+        assert!(lobby.join(PeerId(5), "red", &palette).is_err());
+        assert!(lobby.join(PeerId(5), "Gold", &palette).is_err());
+        assert_eq!(lobby.seat_name(1, &palette), "ada");
+        assert_eq!(lobby.seat_name(3, &palette), "gold");
+        lobby.cycle_seat(2);
+        lobby.cycle_seat(1);
+        assert_eq!(lobby.setup.seats[1], Seat::Human);
+        assert_eq!(lobby.setup.seats[2], Seat::Ai(Difficulty::Easy));
+        assert_eq!(lobby.join(PeerId(7), "bob", &palette), Ok(3));
+        assert!(lobby.join(PeerId(9), "too late", &palette).is_err());
+
+        assert!(lobby.start(&Palette::default()).is_err());
+        lobby.set_ready(PeerId(4), true);
+        lobby.set_ready(PeerId(7), true);
+        let Seating {
+            setup,
+            players,
+            seated,
+        } = lobby.start(&Palette::default()).unwrap();
+        assert_eq!(setup.player_count(), 4);
+        assert_eq!(seated.len(), 2);
+        assert_eq!(seated[&PeerId(4)], PlayerId(1));
+        assert_eq!(seated[&PeerId(7)], PlayerId(3));
+        assert_eq!(players.0[0].controller, Controller::Human);
+        assert_eq!(players.0[1].controller, Controller::Remote);
+        assert_eq!(players.0[2].controller, Controller::Ai(Difficulty::Easy));
+        assert_eq!(players.name(PlayerId(1)), "ada");
+
+        // a seat left open is left out
+        assert_eq!(lobby.leave(PeerId(7)), Some(3));
+        assert!(lobby.is_open(3));
+        assert_eq!(lobby.setup.name(3), None);
+        let seating = lobby.start(&Palette::default()).unwrap();
+        assert_eq!(seating.setup.seats[3], Seat::Empty);
+        assert_eq!(seating.seated.len(), 1);
+
+        // taking seats away sends whoever sat in them home
+        assert_eq!(lobby.join(PeerId(8), "carl", &palette), Ok(3));
+        assert_eq!(lobby.set_seat_count(2), vec![PeerId(8)]);
+        assert_eq!(lobby.seat_of(PeerId(4)), Some(1));
+        assert_eq!(lobby.set_seat_count(0), vec![]);
+        assert_eq!(lobby.setup.seats.len(), 2);
+        lobby.set_seat_count(4);
+        assert!(lobby.is_open(2) && lobby.is_open(3));
+        assert_eq!(lobby.settings.player_count, Some(4));
+        lobby.fill_with_ai();
+        assert_eq!(lobby.setup.seats[3], Seat::Ai(Difficulty::Normal));
+        assert!(lobby.join(PeerId(9), "dee", &palette).is_err());
+
+        let view = client_view(&players, PlayerId(1));
+        assert_eq!(view.0[1].controller, Controller::Human);
+        assert_eq!(view.0[0].controller, Controller::Remote);
+        assert_eq!(view.0[2].controller, Controller::Remote);
+    }
+
+    #[test]
+    fn lobby_keeps_the_map_in_bounds() {
+        let settings = GameSettings {
+            width: 32,
+            height: 24,
+            area_size: 8,
+            ..GameSettings::default()
+        };
+        let mut lobby = Lobby::new(settings, 8);
+        lobby.resize_map(1);
+        assert_eq!((lobby.settings.width, lobby.settings.height), (36, 28));
+        lobby.resize_map(-100);
+        assert_eq!(
+            (lobby.settings.width, lobby.settings.height),
+            (MIN_SIZE, MIN_SIZE)
+        );
+        lobby.resize_map(100);
+        assert_eq!(lobby.settings.width, MAX_SIZE);
+        lobby.resize_areas(-20);
+        assert_eq!(lobby.settings.area_size, MIN_AREA);
+        lobby.resize_areas(20);
+        assert_eq!(lobby.settings.area_size, MAX_AREA);
+    }
+}
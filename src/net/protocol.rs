@@ -0,0 +1,87 @@
+//! What hosts and clients say to each other, and how it goes over the wire.
+//!
+//! Every message is written as RON text, after its length in bytes as four big-endian bytes.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{Lobby, NetError};
+use crate::board::PlayerId;
+use crate::game::Action;
+use crate::replay::Replay;
+
+use std::io::{self, Read, Write};
+
+/// The version of the protocol this build speaks. Hosts turn away clients on any other.
+pub const VERSION: u32 = 2;
+
+/// The longest message either side will read, so a bad length can't ask for all the memory.
+const MAX_MESSAGE: usize = 1 << 20;
+
+/// Sent by a client to the host.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ClientMessage {
+    /// the first thing a client says, and only once
+    Hello { version: u32, name: String },
+    /// whether the client is happy for the game to start, while in the lobby
+    Ready(bool),
+    /// the client would like the next color, while in the lobby
+    NextColor,
+    /// something the client's player wants to do; it only happens once the host sends it back
+    Intent(Action),
+}
+
+/// Sent by the host to a client.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum HostMessage {
+    /// the client can't play, and why; the host hangs up after sending it
+    Refused(String),
+    /// the lobby changed: how it stands now, and which seat the client sits in
+    Lobby { lobby: Box<Lobby>, seat: usize },
+    /// the game is starting, or the client rejoined it: everything about it so far, and which
+    /// player the client is
+    Start { replay: Box<Replay>, you: PlayerId },
+    /// the next action of the game, which the host has already played
+    Resolved(Action),
+    /// the client's last intent wasn't allowed, and why
+    Rejected(String),
+}
+
+/// Write `message` to `writer`, framed.
+pub fn write_message<W: Write, M: Serialize>(writer: &mut W, message: &M) -> Result<(), NetError> {
+    let text = ron::ser::to_string(message).map_err(|error| NetError::Format(error.to_string()))?;
+    let length = text.len();
+    if length > MAX_MESSAGE {
+        return Err(NetError::Format(format!(
+            "message is {} bytes long",
+            length
+        )));
+    }
+    let mut frame = Vec::with_capacity(4 + length);
+    frame.extend_from_slice(&(length as u32).to_be_bytes());
+    frame.extend_from_slice(text.as_bytes());
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read the next message from `reader`, waiting for it. A connection closed between messages is
+/// `NetError::Closed`.
+pub fn read_message<R: Read, M: DeserializeOwned>(reader: &mut R) -> Result<M, NetError> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Err(NetError::Closed),
+        Err(error) => return Err(error.into()),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE {
+        return Err(NetError::Format(format!(
+            "message is {} bytes long",
+            length
+        )));
+    }
+    let mut text = vec![0; length];
+    reader.read_exact(&mut text)?;
+    let text = String::from_utf8(text).map_err(|error| NetError::Format(error.to_string()))?;
+    ron::de::from_str(&text).map_err(|error| NetError::Format(error.to_string()))
+}
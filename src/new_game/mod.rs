@@ -0,0 +1,469 @@
+use amethyst::{
+    assets::Handle,
+    core::ecs::Entity,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{
+        Anchor, FontAsset, Interactable, Selectable, Selected, TextEditing, UiEvent, UiText,
+        UiTransform,
+    },
+};
+
+use crate::assets::HexAssets;
+use crate::config::load_settings;
+use crate::main_menu::MainMenuState;
+use crate::map_selection::MapSelectionState;
+use crate::players::{load_palette, Palette};
+use crate::setup::{GameSetup, Seat};
+
+use std::collections::HashMap;
+
+/// The longest name a player can type in.
+const MAX_NAME_LENGTH: usize = 16;
+
+/// Sets up the seats for a new game: how many players, who sits where, what they are called, their
+/// colors, and who goes first.
+pub struct NewGameState {
+    setup: Option<GameSetup>,
+    buttons: HashMap<Entity, SetupAction>,
+    my_ui: Vec<Entity>,
+    /// a name field and two buttons per seat, rebuilt whenever the seats change
+    seat_buttons: Vec<Entity>,
+    /// the name field of each seat
+    name_fields: Vec<Entity>,
+    count_text: Option<Entity>,
+    first_button: Option<Entity>,
+    message: Option<Entity>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum SetupAction {
+    Fewer,
+    More,
+    /// change who sits in a seat
+    CycleSeat(usize),
+    /// give a seat the next color
+    CycleColor(usize),
+    NextFirst,
+    Start,
+    Back,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (new_game).";
+impl SimpleState for NewGameState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        // start from the last setup, so a rematch keeps the same seats
+        let setup = match world.try_fetch::<GameSetup>() {
+            Some(setup) => setup.clone(),
+            None => {
+                let seats = load_palette(world).players.len();
+                GameSetup::from_settings(&load_settings(world), seats)
+            }
+        };
+        self.setup = Some(setup);
+
+        let title = create_title_text(world, &assets.font, "New Game");
+        let count_text = create_text(world, &assets.font, "count", 0.0, -200.0, 32.0);
+        let message = create_message_text(world, &assets.font);
+        self.my_ui.extend(&[title, count_text, message]);
+        self.count_text = Some(count_text);
+        self.message = Some(message);
+
+        let buttons = [
+            ("< fewer", SetupAction::Fewer, Anchor::TopMiddle, -250.0, -200.0),
+            ("more >", SetupAction::More, Anchor::TopMiddle, 250.0, -200.0),
+            ("first", SetupAction::NextFirst, Anchor::BottomMiddle, 0.0, 100.0),
+            ("back", SetupAction::Back, Anchor::BottomMiddle, -150.0, 30.0),
+            ("start", SetupAction::Start, Anchor::BottomMiddle, 150.0, 30.0),
+        ];
+        for (text, action, anchor, x, y) in buttons.iter() {
+            let button = create_button(world, &assets.font, text, *anchor, *x, *y);
+            self.buttons.insert(button, *action);
+            self.my_ui.push(button);
+            if let SetupAction::NextFirst = action {
+                self.first_button = Some(button);
+            }
+        }
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        data.world
+            .delete_entities(&self.seat_buttons)
+            .expect(EXPECTED);
+        self.my_ui.clear();
+        self.seat_buttons.clear();
+        self.name_fields.clear();
+        self.buttons.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        mut data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            // keys go to the name being typed
+            if self.is_typing(&data.world) {
+                return Trans::None;
+            }
+
+            let keys = [
+                (VirtualKeyCode::Escape, SetupAction::Back),
+                (VirtualKeyCode::Left, SetupAction::Fewer),
+                (VirtualKeyCode::Right, SetupAction::More),
+                (VirtualKeyCode::Return, SetupAction::Start),
+            ];
+            for (key, action) in keys.iter() {
+                if is_key_down(&event, *key) {
+                    return self.perform(&mut data.world, *action);
+                }
+            }
+        }
+        if let StateEvent::Ui(event) = &event {
+            use amethyst::ui::UiEventType::*;
+            let UiEvent { event_type, target } = event;
+            match event_type {
+                Click => {
+                    if let Some(action) = self.buttons.get(target).cloned() {
+                        return self.perform(&mut data.world, action);
+                    }
+                }
+                _ => {
+                    //
+                }
+            }
+        }
+        Trans::None
+    }
+}
+
+impl NewGameState {
+    pub fn new() -> Self {
+        NewGameState {
+            setup: None,
+            buttons: HashMap::new(),
+            my_ui: vec![],
+            seat_buttons: vec![],
+            name_fields: vec![],
+            count_text: None,
+            first_button: None,
+            message: None,
+        }
+    }
+    pub fn new_boxed() -> Box<Self> {
+        Box::new(NewGameState::new())
+    }
+
+    fn perform(&mut self, world: &mut World, action: SetupAction) -> SimpleTrans {
+        // pick up any names typed in before the fields are rebuilt
+        self.read_names(world);
+        let setup = match &mut self.setup {
+            Some(setup) => setup,
+            None => return Trans::None,
+        };
+        match action {
+            SetupAction::Fewer => {
+                let count = setup.seats.len() - 1;
+                setup.set_seat_count(count);
+            }
+            SetupAction::More => {
+                let count = setup.seats.len() + 1;
+                setup.set_seat_count(count);
+            }
+            SetupAction::CycleSeat(seat) => {
+                setup.seats[seat] = setup.seats[seat].next();
+            }
+            SetupAction::CycleColor(seat) => {
+                setup.next_color(seat);
+            }
+            SetupAction::NextFirst => {
+                setup.next_first();
+            }
+            SetupAction::Start => {
+                if !setup.is_playable() {
+                    let why = if setup.seats[setup.first] == Seat::Empty {
+                        "the first seat must be filled"
+                    } else {
+                        "a game needs at least two players"
+                    };
+                    self.set_text(world, self.message, why);
+                    return Trans::None;
+                }
+                world.insert(setup.clone());
+                return Trans::Switch(MapSelectionState::new_boxed());
+            }
+            SetupAction::Back => {
+                return Trans::Switch(MainMenuState::new_boxed());
+            }
+        }
+        self.set_text(world, self.message, "");
+        self.refresh(world);
+        Trans::None
+    }
+
+    /// Rebuild the seat buttons and captions to match the setup.
+    fn refresh(&mut self, world: &mut World) {
+        let setup = match &self.setup {
+            Some(setup) => setup.clone(),
+            None => return,
+        };
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+
+        for entity in self.seat_buttons.drain(..) {
+            self.buttons.remove(&entity);
+            world.delete_entity(entity).expect(EXPECTED);
+        }
+        self.name_fields.clear();
+        let palette = load_palette(world);
+        for (i, seat) in setup.seats.iter().enumerate() {
+            let y = -260.0 - i as f32 * 40.0;
+            let name = default_name(&setup, &palette, i);
+            let name = setup.name(i).unwrap_or(&name);
+            let field = create_name_field(world, &assets.font, name, i, y);
+            let text = seat.to_string();
+            let button = create_button(world, &assets.font, &text, Anchor::TopMiddle, 0.0, y);
+            let (color_name, color) = match palette.players.get(setup.color(i)) {
+                Some(entry) => (entry.name.clone(), entry.color),
+                None => ("color".to_string(), (0.5, 0.5, 0.5)),
+            };
+            let color_button = create_color_button(world, &assets.font, &color_name, color, y);
+            self.buttons.insert(button, SetupAction::CycleSeat(i));
+            self.buttons.insert(color_button, SetupAction::CycleColor(i));
+            self.seat_buttons.extend(&[field, button, color_button]);
+            self.name_fields.push(field);
+        }
+
+        let count = format!("{} of {} seats", setup.seats.len(), setup.max_seats);
+        let first = format!("seat {} goes first", setup.first + 1);
+        self.set_text(world, self.count_text, &count);
+        self.set_text(world, self.first_button, &first);
+    }
+
+    /// Copy the names in the name fields into the setup. A name left as the color's name isn't
+    /// kept, so it follows the color if that changes.
+    fn read_names(&mut self, world: &World) {
+        let setup = match &mut self.setup {
+            Some(setup) => setup,
+            None => return,
+        };
+        let palette = load_palette(world);
+        let texts = world.read_storage::<UiText>();
+        for (i, field) in self.name_fields.iter().enumerate() {
+            if let Some(text) = texts.get(*field) {
+                if text.text.trim() == default_name(setup, &palette, i) {
+                    setup.set_name(i, "");
+                } else {
+                    setup.set_name(i, &text.text);
+                }
+            }
+        }
+    }
+
+    /// Whether a name field has the keyboard.
+    fn is_typing(&self, world: &World) -> bool {
+        let selected = world.read_storage::<Selected>();
+        self.name_fields.iter().any(|&field| selected.contains(field))
+    }
+
+    fn set_text(&self, world: &mut World, entity: Option<Entity>, text: &str) {
+        if let Some(entity) = entity {
+            let mut texts = world.write_storage::<UiText>();
+            texts.get_mut(entity).expect(EXPECTED).text = text.to_string();
+        }
+    }
+}
+
+fn create_title_text(world: &mut World, font: &Handle<FontAsset>, text: &str) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        -100.0,
+        1.0,
+        800.0,
+        75.0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            96.0,
+        ))
+        .build();
+
+    text
+}
+
+fn create_text(world: &mut World, font: &Handle<FontAsset>, id: &str, x: f32, y: f32, size: f32) -> Entity {
+    let transform = UiTransform::new(
+        id.to_string(),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        x,
+        y,
+        1.0,
+        300.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            size,
+        ))
+        .build()
+}
+
+fn create_message_text(world: &mut World, font: &Handle<FontAsset>) -> Entity {
+    let transform = UiTransform::new(
+        "message".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.0,
+        150.0,
+        1.0,
+        600.0,
+        30.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.6, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+fn create_button(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    anchor: Anchor,
+    x: f32,
+    y: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        text.to_string(),
+        anchor,
+        Anchor::Middle,
+        x,
+        y,
+        1.0,
+        300.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+/// An editable text box with a seat's name in it.
+fn create_name_field(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    name: &str,
+    seat: usize,
+    y: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        format!("name {}", seat),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        -290.0,
+        y,
+        1.0,
+        220.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            name.to_string(),
+            [0.1, 0.1, 0.1, 1.0],
+            20.0,
+        ))
+        .with(TextEditing::new(
+            MAX_NAME_LENGTH,
+            [0.9, 0.9, 0.9, 1.0],
+            [0.1, 0.1, 0.1, 1.0],
+            false,
+        ))
+        .with(Interactable)
+        .with(Selectable::<()>::new(seat as u32))
+        .build()
+}
+
+/// A button showing the name of a seat's color, written in that color.
+fn create_color_button(
+    world: &mut World,
+    font: &Handle<FontAsset>,
+    text: &str,
+    color: (f32, f32, f32),
+    y: f32,
+) -> Entity {
+    let transform = UiTransform::new(
+        format!("color {}", text),
+        Anchor::TopMiddle,
+        Anchor::Middle,
+        260.0,
+        y,
+        1.0,
+        180.0,
+        40.0,
+    );
+
+    let (r, g, b) = color;
+    world
+        .create_entity()
+        .with(Interactable)
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            text.to_string(),
+            [r, g, b, 1.0],
+            20.0,
+        ))
+        .build()
+}
+
+/// What a seat is called when nobody typed in a name: the name of its color.
+fn default_name(setup: &GameSetup, palette: &Palette, seat: usize) -> String {
+    match palette.players.get(setup.color(seat)) {
+        Some(entry) => entry.name.clone(),
+        None => format!("seat {}", seat + 1),
+    }
+}
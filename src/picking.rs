@@ -0,0 +1,120 @@
+//! Turns the mouse cursor into a position on the hex grid.
+
+use amethyst::{
+    core::{
+        ecs::{Join, Read, ReadExpect, ReadStorage, System, Write},
+        math::Point3,
+        shrev::EventChannel,
+        transform::Transform,
+        Hidden,
+    },
+    input::{InputHandler, StringBindings},
+    renderer::Camera,
+    ui::{Interactable, UiTransform},
+    window::ScreenDimensions,
+    winit::MouseButton,
+};
+
+use crate::game::Game;
+use crate::hex::{Hex, Layout};
+
+/// Emitted by `HexPickingSystem` as the mouse moves over and clicks on the board.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HexEvent {
+    /// the cursor moved onto a different hex of the board, or off the board entirely
+    Hovered(Option<Hex>),
+    /// the left mouse button was pressed over a hex of the board
+    Clicked(Hex),
+}
+
+/// Picking state shared with the states. Nothing is picked while `layout` is `None`, and only hexes
+/// on the board of the `Game` resource are picked.
+#[derive(Default)]
+pub struct HexPicking {
+    /// the layout of the hexes on screen
+    pub layout: Option<Layout>,
+    /// the hex currently under the cursor
+    pub hovered: Option<Hex>,
+}
+
+/// Finds the hex under the cursor through the camera, so panning and zooming are respected.
+/// Nothing is picked while the cursor is over a button, so clicking one doesn't click the board.
+#[derive(Default)]
+pub struct HexPickingSystem {
+    was_down: bool,
+}
+
+impl<'s> System<'s> for HexPickingSystem {
+    type SystemData = (
+        Read<'s, InputHandler<StringBindings>>,
+        ReadExpect<'s, ScreenDimensions>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, UiTransform>,
+        ReadStorage<'s, Interactable>,
+        ReadStorage<'s, Hidden>,
+        Option<Read<'s, Game>>,
+        Write<'s, HexPicking>,
+        Write<'s, EventChannel<HexEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            input,
+            dimensions,
+            cameras,
+            transforms,
+            ui_transforms,
+            interactables,
+            hiddens,
+            game,
+            mut picking,
+            mut events,
+        ): Self::SystemData,
+    ) {
+        let down = input.mouse_button_is_down(MouseButton::Left);
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+
+        let layout = match picking.layout {
+            Some(layout) => layout,
+            None => return,
+        };
+
+        // the ui measures y from the bottom of the screen
+        let over_ui = input.mouse_position().map_or(false, |(x, y)| {
+            let y = dimensions.height() - y;
+            (&ui_transforms, &interactables, !&hiddens)
+                .join()
+                .any(|(ui, _, _)| ui.position_inside(x, y))
+        });
+
+        let hex = input
+            .mouse_position()
+            .filter(|_| !over_ui)
+            .and_then(|(x, y)| {
+                let (camera, transform) = (&cameras, &transforms).join().next()?;
+                let point = camera.projection().screen_to_world_point(
+                    Point3::new(x, y, 0.0),
+                    dimensions.diagonal(),
+                    transform,
+                );
+                let hex = layout.pixel_to_hex(point.x, point.y);
+                // the grid goes on forever, the board doesn't
+                if game.as_ref()?.board().in_bounds(hex) {
+                    Some(hex)
+                } else {
+                    None
+                }
+            });
+
+        if hex != picking.hovered {
+            picking.hovered = hex;
+            events.single_write(HexEvent::Hovered(hex));
+        }
+        if let (true, Some(hex)) = (pressed, hex) {
+            events.single_write(HexEvent::Clicked(hex));
+        }
+    }
+}
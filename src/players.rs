@@ -0,0 +1,171 @@
+//! The people and AIs playing: names, colors and who controls them.
+//!
+//! Names and colors come from `palette.ron`, so there can be as many players as the palette has
+//! entries.
+
+use amethyst::{
+    assets::{Asset, AssetStorage, Handle, ProcessingState},
+    core::ecs::{DenseVecStorage, World},
+    Result,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::assets::HexAssets;
+use crate::board::PlayerId;
+use crate::setup::{Difficulty, GameSetup, Seat};
+
+use std::fmt;
+
+/// The names and colors players are given, one entry per seat.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct Palette {
+    pub players: Vec<PaletteEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PaletteEntry {
+    pub name: String,
+    /// red, green and blue, from 0.0 to 1.0
+    pub color: (f32, f32, f32),
+    /// players on the same team; nobody is on a team unless the palette says so
+    #[serde(default)]
+    pub team: Option<u32>,
+}
+
+impl Asset for Palette {
+    const NAME: &'static str = "hexadie::Palette";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<Handle<Palette>>;
+}
+
+impl From<Palette> for Result<ProcessingState<Palette>> {
+    fn from(palette: Palette) -> Result<ProcessingState<Palette>> {
+        Ok(ProcessingState::Loaded(palette))
+    }
+}
+
+/// The palette in `palette.ron`. The assets must have finished loading.
+pub fn load_palette(world: &World) -> Palette {
+    let assets = world.read_resource::<HexAssets>();
+    let asset_storage = world.read_resource::<AssetStorage<Palette>>();
+    asset_storage
+        .get(&assets.palette)
+        .expect("failed to load palette")
+        .clone()
+}
+
+/// Who decides a player's moves.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Controller {
+    Human,
+    Ai(Difficulty),
+    /// someone playing over the network; their moves come from the host, or go to it
+    Remote,
+}
+
+impl fmt::Display for Controller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Controller::Human => write!(f, "human"),
+            Controller::Ai(difficulty) => write!(f, "{}", Seat::Ai(*difficulty)),
+            Controller::Remote => write!(f, "remote"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PlayerDescriptor {
+    pub id: PlayerId,
+    pub name: String,
+    pub color: (f32, f32, f32),
+    pub controller: Controller,
+    pub team: Option<u32>,
+}
+
+/// Everyone in the current game, indexed by player id. Inserted as a resource once the seats are
+/// chosen.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Players(pub Vec<PlayerDescriptor>);
+
+impl Players {
+    /// Describe the occupied seats of `setup`. Each seat takes the color it was given, and the
+    /// name typed in for it or else the name of its color. Teams go by seat, whatever the color.
+    pub fn from_setup(setup: &GameSetup, palette: &Palette) -> Players {
+        let players = setup
+            .seats
+            .iter()
+            .enumerate()
+            .filter_map(|(seat, &sitting)| {
+                let controller = match sitting {
+                    Seat::Human => Controller::Human,
+                    Seat::Ai(difficulty) => Controller::Ai(difficulty),
+                    Seat::Empty => return None,
+                };
+                Some((seat, controller))
+            })
+            .enumerate()
+            .map(|(id, (seat, controller))| {
+                let entry = palette.players.get(setup.color(seat));
+                let name = match setup.name(seat) {
+                    Some(name) => name.to_string(),
+                    None => entry.map_or_else(|| format!("player {}", id + 1), |e| e.name.clone()),
+                };
+                PlayerDescriptor {
+                    id: PlayerId(id),
+                    name,
+                    color: entry.map_or(UNKNOWN, |e| e.color),
+                    controller,
+                    team: palette.players.get(seat).and_then(|e| e.team),
+                }
+            })
+            .collect();
+        Players(players)
+    }
+
+    pub fn get(&self, player: PlayerId) -> Option<&PlayerDescriptor> {
+        self.0.get(player.0)
+    }
+
+    pub fn name(&self, player: PlayerId) -> String {
+        self.get(player)
+            .map_or_else(|| format!("player {}", player.0 + 1), |p| p.name.clone())
+    }
+
+    pub fn color(&self, player: PlayerId) -> (f32, f32, f32) {
+        self.get(player).map_or(UNKNOWN, |p| p.color)
+    }
+
+    /// Whether more than one of `players` is played by a person, so they take turns at the same
+    /// screen.
+    pub fn is_hot_seat(&self, players: &[PlayerId]) -> bool {
+        let humans = players
+            .iter()
+            .filter_map(|&player| self.get(player))
+            .filter(|p| p.controller == Controller::Human)
+            .count();
+        humans > 1
+    }
+
+    /// Who has won, given the players still on the board: the last one standing, or everyone
+    /// left if they are all on the same team. `None` while the game is still going.
+    pub fn winners(&self, remaining: &[PlayerId]) -> Option<Vec<PlayerId>> {
+        let team = |player: PlayerId| self.get(player).and_then(|p| p.team);
+        let decided = match remaining.split_first() {
+            Some((&first, rest)) => {
+                let same_team =
+                    team(first).is_some() && rest.iter().all(|&p| team(p) == team(first));
+                rest.is_empty() || same_team
+            }
+            None => false,
+        };
+        if decided {
+            Some(remaining.to_vec())
+        } else {
+            None
+        }
+    }
+}
+
+/// The color of a player the palette has no entry for.
+const UNKNOWN: (f32, f32, f32) = (0.5, 0.5, 0.5);
@@ -0,0 +1,100 @@
+//! Polygons for drawing territories: the outline of a group of hexes, the triangles that fill
+//! it, and strips of triangles that stroke it.
+
+use crate::board::{Board, TerritoryId};
+use crate::hex::Layout;
+
+use std::collections::HashMap;
+
+pub type Point = (f32, f32);
+pub type Triangle = [Point; 3];
+
+/// The outline of a territory as closed loops of points, counter-clockwise.
+/// A territory that surrounds other hexes has one loop for its outside and one per hole.
+pub fn territory_outline(board: &Board, layout: &Layout, territory: TerritoryId) -> Vec<Vec<Point>> {
+    // every hex edge that doesn't face another hex of this territory is on the outline.
+    // hex corners run counter-clockwise, so each edge leads into the next one around the loop.
+    let mut edges: HashMap<PointKey, Point> = HashMap::new();
+    for &hex in board.territory(territory).hexes() {
+        let corners = layout.corners(hex);
+        for direction in 0..6 {
+            if board.territory_at(hex.neighbor(direction)) != Some(territory) {
+                let start = corners[direction];
+                let end = corners[(direction + 1) % 6];
+                edges.insert(PointKey::new(start), end);
+            }
+        }
+    }
+
+    let mut loops = vec![];
+    while let Some((&first, _)) = edges.iter().next() {
+        let mut outline = vec![];
+        let mut key = first;
+        while let Some(end) = edges.remove(&key) {
+            outline.push(key.point());
+            key = PointKey::new(end);
+        }
+        loops.push(outline);
+    }
+    loops
+}
+
+/// Triangles covering every hex of a territory.
+pub fn territory_fill(board: &Board, layout: &Layout, territory: TerritoryId) -> Vec<Triangle> {
+    let mut triangles = vec![];
+    for &hex in board.territory(territory).hexes() {
+        let center = layout.hex_to_pixel(hex);
+        let corners = layout.corners(hex);
+        for i in 0..6 {
+            triangles.push([center, corners[i], corners[(i + 1) % 6]]);
+        }
+    }
+    triangles
+}
+
+/// Triangles drawing a line `width` wide along each closed loop.
+pub fn stroke(loops: &[Vec<Point>], width: f32) -> Vec<Triangle> {
+    let half = width / 2.0;
+    let mut triangles = vec![];
+    for outline in loops {
+        for (i, &a) in outline.iter().enumerate() {
+            let b = outline[(i + 1) % outline.len()];
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let length = (dx * dx + dy * dy).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            // run each segment half a line width past its ends so the corners join up
+            let (ux, uy) = (dx / length * half, dy / length * half);
+            let (nx, ny) = (-uy, ux);
+            let a = (a.0 - ux, a.1 - uy);
+            let b = (b.0 + ux, b.1 + uy);
+            let quad = [
+                (a.0 + nx, a.1 + ny),
+                (a.0 - nx, a.1 - ny),
+                (b.0 - nx, b.1 - ny),
+                (b.0 + nx, b.1 + ny),
+            ];
+            triangles.push([quad[0], quad[1], quad[2]]);
+            triangles.push([quad[0], quad[2], quad[3]]);
+        }
+    }
+    triangles
+}
+
+/// A point rounded to a hundredth of a unit, so corners computed from neighboring hexes match.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct PointKey(i64, i64);
+
+impl PointKey {
+    fn new(point: Point) -> PointKey {
+        PointKey(
+            (point.0 * 100.0).round() as i64,
+            (point.1 * 100.0).round() as i64,
+        )
+    }
+
+    fn point(self) -> Point {
+        (self.0 as f32 / 100.0, self.1 as f32 / 100.0)
+    }
+}
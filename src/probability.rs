@@ -0,0 +1,159 @@
+//! Exact odds of winning an attack.
+//!
+//! An attack is won when the attacker's dice add up to more than the defender's, so the odds
+//! only depend on how many dice each side rolls. They are worked out from the distribution of
+//! dice sums, and kept in a table for every count a territory can hold.
+
+use crate::rules::{DIE_FACES, MAX_DICE};
+
+/// The chance that `attacker` dice roll a higher total than `defender` dice.
+pub fn win_probability(attacker: u32, defender: u32) -> f64 {
+    if attacker <= MAX_DICE && defender <= MAX_DICE {
+        ODDS.with(|odds| odds.get(attacker, defender))
+    } else {
+        compute(attacker, defender)
+    }
+}
+
+thread_local! {
+    static ODDS: OddsTable = OddsTable::new(MAX_DICE);
+}
+
+/// Winning odds for every pair of dice counts up to a maximum.
+#[derive(Clone, Debug)]
+pub struct OddsTable {
+    max_dice: u32,
+    /// indexed by `attacker * (max_dice + 1) + defender`
+    odds: Vec<f64>,
+}
+
+impl OddsTable {
+    pub fn new(max_dice: u32) -> OddsTable {
+        let sums: Vec<Vec<f64>> = (0..=max_dice).map(sum_distribution).collect();
+        let mut odds = vec![];
+        for attacker in 0..=max_dice {
+            for defender in 0..=max_dice {
+                odds.push(beats(&sums[attacker as usize], &sums[defender as usize]));
+            }
+        }
+        OddsTable { max_dice, odds }
+    }
+
+    pub fn get(&self, attacker: u32, defender: u32) -> f64 {
+        assert!(
+            attacker <= self.max_dice && defender <= self.max_dice,
+            "{} against {} dice is outside the odds table",
+            attacker,
+            defender
+        );
+        self.odds[(attacker * (self.max_dice + 1) + defender) as usize]
+    }
+}
+
+fn compute(attacker: u32, defender: u32) -> f64 {
+    beats(&sum_distribution(attacker), &sum_distribution(defender))
+}
+
+/// The chance of each total when rolling `count` dice, indexed by the total.
+pub fn sum_distribution(count: u32) -> Vec<f64> {
+    let faces = DIE_FACES as usize;
+    // no dice always add up to zero
+    let mut distribution = vec![1.0];
+    for _ in 0..count {
+        let mut next = vec![0.0; distribution.len() + faces];
+        for (total, &chance) in distribution.iter().enumerate() {
+            for face in 1..=faces {
+                next[total + face] += chance / faces as f64;
+            }
+        }
+        distribution = next;
+    }
+    distribution
+}
+
+/// The chance that a total drawn from `attacker` is strictly higher than one drawn from `defender`.
+fn beats(attacker: &[f64], defender: &[f64]) -> f64 {
+    // below[total] is the chance the defender rolls less than `total`
+    let mut below = vec![0.0; attacker.len()];
+    let mut running = 0.0;
+    for (total, slot) in below.iter_mut().enumerate() {
+        *slot = running;
+        running += defender.get(total).cloned().unwrap_or(0.0);
+    }
+    attacker
+        .iter()
+        .zip(below.iter())
+        .map(|(&chance, &below)| chance * below)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::roll;
+    use crate::seed::GameRng;
+
+    use rand::SeedableRng;
+
+    #[test]
+    fn distributions_add_up_to_one() {
+        for count in 0..=MAX_DICE {
+            let total: f64 = sum_distribution(count).iter().sum();
+            assert!((total - 1.0).abs() < 1e-9, "{} dice add up to {}", count, total);
+        }
+    }
+
+    #[test]
+    fn small_cases_match_counting_by_hand() {
+        // one die beats another in 15 of the 36 ways they can land
+        assert!((win_probability(1, 1) - 15.0 / 36.0).abs() < 1e-12);
+        // two dice beat one in 181 of 216 ways
+        assert!((win_probability(2, 1) - 181.0 / 216.0).abs() < 1e-12);
+        assert!(win_probability(0, 1).abs() < 1e-12);
+        assert!((win_probability(1, 0) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ties_favor_the_defender() {
+        for dice in 1..=MAX_DICE {
+            assert!(win_probability(dice, dice) < 0.5);
+        }
+    }
+
+    #[test]
+    fn table_matches_direct_computation() {
+        let table = OddsTable::new(MAX_DICE);
+        for attacker in 0..=MAX_DICE {
+            for defender in 0..=MAX_DICE {
+                let direct = compute(attacker, defender);
+                assert!((table.get(attacker, defender) - direct).abs() < 1e-12);
+            }
+        }
+        assert!((win_probability(10, 9) - compute(10, 9)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn matches_monte_carlo() {
+        let mut rng = GameRng::seed_from_u64(0x0dd5);
+        let trials = 20_000;
+        for &(attacker, defender) in &[(1, 1), (2, 1), (3, 3), (4, 6), (8, 8), (8, 1), (5, 7)] {
+            let wins = (0..trials)
+                .filter(|_| {
+                    let a: u32 = roll(attacker, &mut rng).iter().map(|&f| f as u32).sum();
+                    let d: u32 = roll(defender, &mut rng).iter().map(|&f| f as u32).sum();
+                    a > d
+                })
+                .count();
+            let simulated = wins as f64 / trials as f64;
+            let exact = win_probability(attacker, defender);
+            assert!(
+                (simulated - exact).abs() < 0.015,
+                "{} against {}: simulated {}, exact {}",
+                attacker,
+                defender,
+                simulated,
+                exact
+            );
+        }
+    }
+}
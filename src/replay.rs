@@ -0,0 +1,283 @@
+//! Replays: a game written down as its seed, settings and every action taken, kept as RON files
+//! in the `replays` folder.
+//!
+//! Maps and dice both come from the seed, so playing the same actions through the rules again
+//! gives back exactly the same game, and a whole game fits in a few kilobytes.
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameSettings;
+use crate::game::{Action, ActionOutcome, Game};
+use crate::mapgen;
+use crate::players::Players;
+use crate::rules::{Attack, AttackError};
+use crate::seed::MapSeed;
+use crate::setup::GameSetup;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The version of the replay format this build writes and reads.
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: MapSeed,
+    /// the settings the map was generated from, with the player count filled in
+    pub settings: GameSettings,
+    pub setup: GameSetup,
+    pub players: Players,
+    pub actions: Vec<Action>,
+}
+
+/// Just the version, for telling why a replay didn't load.
+#[derive(Deserialize)]
+#[serde(rename = "Replay")]
+struct Header {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// the file isn't a replay, or is damaged
+    Format(String),
+    /// the file was recorded by a different version of the game
+    Version(u32),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{}", error),
+            ReplayError::Format(error) => write!(f, "not a replay: {}", error),
+            ReplayError::Version(version) => write!(
+                f,
+                "recorded by another version of the game (replay version {}, expected {})",
+                version, VERSION
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> ReplayError {
+        ReplayError::Io(error)
+    }
+}
+
+impl Replay {
+    /// Record `game` so far. `settings` must be the ones its map was generated from.
+    pub fn record(
+        seed: MapSeed,
+        settings: GameSettings,
+        setup: GameSetup,
+        players: Players,
+        game: &Game,
+    ) -> Replay {
+        Replay {
+            version: VERSION,
+            seed,
+            settings,
+            setup,
+            players,
+            actions: game.history().to_vec(),
+        }
+    }
+
+    /// The game as it was before anyone moved.
+    pub fn start(&self) -> Game {
+        let board = mapgen::generate(&self.settings, &mut self.seed.rng()).board;
+        Game::new(board, self.setup.turn_order(), self.seed.game_rng())
+    }
+
+    /// Play the action at `step` on `game`, which should be the game after the steps before it.
+    /// Returns `None` once there are no more actions.
+    pub fn play(&self, game: &mut Game, step: usize) -> Option<Result<ActionOutcome, AttackError>> {
+        self.actions.get(step).map(|&action| game.apply(action))
+    }
+
+    /// The game after the first `steps` actions. An action the rules refuse changes nothing, so
+    /// it is passed over.
+    pub fn game_at(&self, steps: usize) -> Game {
+        let mut game = self.start();
+        for step in 0..steps.min(self.actions.len()) {
+            let _ = self.play(&mut game, step);
+        }
+        game
+    }
+
+    /// Make sure every attack is between territories on the replay's map, so a damaged file
+    /// can't name one that isn't there.
+    fn check_territories(self) -> Result<Replay, ReplayError> {
+        let territory_count = self.start().board().territory_count();
+        let off_the_map = self.actions.iter().position(|action| match action {
+            Action::Attack(Attack { from, to }) => {
+                from.0 >= territory_count || to.0 >= territory_count
+            }
+            Action::EndTurn => false,
+        });
+        match off_the_map {
+            Some(step) => Err(ReplayError::Format(format!(
+                "action {} attacks a territory that isn't on the map",
+                step
+            ))),
+            None => Ok(self),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, ReplayError> {
+        // one action to a line keeps long games short, and still easy to read
+        let config = PrettyConfig {
+            depth_limit: 3,
+            ..PrettyConfig::default()
+        };
+        ron::ser::to_string_pretty(self, config)
+            .map_err(|error| ReplayError::Format(error.to_string()))
+    }
+
+    pub fn from_ron(text: &str) -> Result<Replay, ReplayError> {
+        match ron::de::from_str::<Replay>(text) {
+            Ok(replay) if replay.version == VERSION => replay.check_territories(),
+            Ok(replay) => Err(ReplayError::Version(replay.version)),
+            Err(error) => match ron::de::from_str::<Header>(text) {
+                Ok(header) if header.version != VERSION => {
+                    Err(ReplayError::Version(header.version))
+                }
+                _ => Err(ReplayError::Format(error.to_string())),
+            },
+        }
+    }
+
+    /// Write the replay to `path`, making its folder if need be.
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        Replay::from_ron(&fs::read_to_string(path)?)
+    }
+}
+
+/// The folder replays are kept in, under the game's root folder.
+pub fn replay_dir(root: &Path) -> PathBuf {
+    root.join("replays")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{HeuristicAi, Strategy};
+    use crate::board::{PlayerId, TerritoryId};
+    use crate::players::{Controller, PlayerDescriptor};
+    use crate::setup::{Difficulty, Seat};
+
+    #[test]
+    fn replays_to_the_same_game() {
+        let seed = MapSeed(0x4e91a);
+        let setup = GameSetup {
+            seats: vec![Seat::Ai(Difficulty::Easy), Seat::Ai(Difficulty::Normal)],
+            first: 1,
+            max_seats: 8,
+            names: vec![],
+            colors: vec![],
+        };
+        let settings = GameSettings {
+            width: 16,
+            height: 16,
+            area_size: 6,
+            player_count: Some(setup.player_count() as u32),
+            ..GameSettings::default()
+        };
+        let players = Players(
+            (0..2)
+                .map(|id| PlayerDescriptor {
+                    id: PlayerId(id),
+                    name: format!("player {}", id + 1),
+                    color: (0.5, 0.5, 0.5),
+                    controller: Controller::Ai(Difficulty::Normal),
+                    team: None,
+                })
+                .collect(),
+        );
+
+        // play the game through once, as the gameplay state would
+        let board = mapgen::generate(&settings, &mut seed.rng()).board;
+        let mut game = Game::new(board, setup.turn_order(), seed.game_rng());
+        for _ in 0..300 {
+            if game.winner().is_some() {
+                break;
+            }
+            let action = HeuristicAi::new(0.5).next_action(game.board(), game.current_player());
+            game.apply(action).unwrap();
+        }
+
+        let replay = Replay::record(seed, settings, setup, players, &game);
+        let replay = Replay::from_ron(&replay.to_ron().unwrap()).unwrap();
+        assert_eq!(replay.game_at(replay.actions.len()), game);
+
+        // stepping one action at a time gets to the same places
+        let mut stepped = replay.start();
+        for step in 0..replay.actions.len() {
+            replay.play(&mut stepped, step).unwrap().unwrap();
+            if step % 25 == 0 {
+                assert_eq!(replay.game_at(step + 1), stepped);
+            }
+        }
+        assert_eq!(stepped, game);
+    }
+
+    #[test]
+    fn refuses_attacks_off_the_map() {
+        let mut replay = Replay {
+            version: VERSION,
+            seed: MapSeed(7),
+            settings: GameSettings {
+                width: 8,
+                height: 8,
+                area_size: 4,
+                player_count: Some(2),
+                ..GameSettings::default()
+            },
+            setup: GameSetup {
+                seats: vec![Seat::Ai(Difficulty::Easy), Seat::Ai(Difficulty::Easy)],
+                first: 0,
+                max_seats: 8,
+                names: vec![],
+                colors: vec![],
+            },
+            players: Players(vec![]),
+            actions: vec![Action::EndTurn],
+        };
+        let text = replay.to_ron().unwrap();
+        assert!(Replay::from_ron(&text).is_ok());
+
+        let territory_count = replay.start().board().territory_count();
+        replay.actions.push(Action::Attack(Attack {
+            from: TerritoryId(0),
+            to: TerritoryId(territory_count),
+        }));
+        match Replay::from_ron(&replay.to_ron().unwrap()) {
+            Err(ReplayError::Format(_)) => {}
+            other => panic!("expected a format error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn refuses_garbage() {
+        match Replay::from_ron("Palette(players: [])") {
+            Err(error @ ReplayError::Format(_)) => {
+                assert!(error.to_string().starts_with("not a replay"))
+            }
+            other => panic!("expected a format error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
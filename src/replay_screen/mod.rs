@@ -0,0 +1,254 @@
+use amethyst::{
+    assets::Handle,
+    core::{ecs::Entity, timing::Time},
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, FontAsset, UiText, UiTransform},
+};
+
+use crate::assets::HexAssets;
+use crate::board_view::BoardView;
+use crate::config::load_settings;
+use crate::entities::focus_camera;
+use crate::game::{ActionOutcome, Game};
+use crate::hex::Layout;
+use crate::load_game::LoadGameState;
+use crate::picking::HexPicking;
+use crate::players::Players;
+use crate::replay::Replay;
+use crate::rules::AttackError;
+
+/// Seconds between actions while playing on its own, at normal speed.
+const STEP_TIME: f32 = 0.5;
+
+/// Plays back a recorded game on the board, an action at a time.
+///
+/// Right and left arrows step forward and back, space plays on its own. Stepping back replays the
+/// game from the start, since the rules can only go forward.
+pub struct ReplayState {
+    replay: Replay,
+    /// how many actions have been played
+    step: usize,
+    /// seconds since the last action while playing on its own, or `None` while paused
+    autoplay: Option<f32>,
+    speed: f32,
+    view: Option<BoardView>,
+    progress: Option<Entity>,
+    description: Option<Entity>,
+    my_ui: Vec<Entity>,
+}
+
+const EXPECTED: &'static str = "Expected UI entity or component not found (replay_screen).";
+impl SimpleState for ReplayState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let assets = (*world.read_resource::<HexAssets>()).clone();
+        self.speed = load_settings(world).animation_speed.unwrap_or(1.0).max(0.1);
+        let game = self.replay.start();
+        focus_camera(world, game.board());
+        world.insert(self.replay.players.clone());
+        self.view = Some(BoardView::spawn(world, game.board()));
+        world.insert(game);
+        // lets the camera pan and zoom; nothing listens for clicks
+        world.write_resource::<HexPicking>().layout = Some(Layout::BOARD);
+
+        let progress = create_text(world, &assets.font, "progress", -20.0, 28.0);
+        let description = create_text(world, &assets.font, "description", -60.0, 20.0);
+        let help = create_text(world, &assets.font, "help", -100.0, 16.0);
+        self.my_ui.extend(&[progress, description, help]);
+        self.progress = Some(progress);
+        self.description = Some(description);
+        self.set_text(
+            world,
+            Some(help),
+            "right/left: step forward/back, space: play/pause, escape: leave",
+        );
+        self.show(world, None);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<HexPicking>() = HexPicking::default();
+        if let Some(view) = self.view.take() {
+            view.despawn(data.world);
+        }
+        data.world.delete_entities(&self.my_ui).expect(EXPECTED);
+        self.my_ui.clear();
+    }
+
+    fn handle_event(
+        &mut self,
+        mut data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Switch(LoadGameState::replays_boxed());
+            }
+            if is_key_down(&event, VirtualKeyCode::Right) {
+                self.autoplay = None;
+                self.step_forward(&mut data.world);
+            }
+            if is_key_down(&event, VirtualKeyCode::Left) {
+                self.autoplay = None;
+                self.step_back(&mut data.world);
+            }
+            if is_key_down(&event, VirtualKeyCode::Space) {
+                self.autoplay = match self.autoplay {
+                    Some(_) => None,
+                    None => Some(0.0),
+                };
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let world = &mut *data.world;
+        if let Some(elapsed) = self.autoplay {
+            let elapsed = elapsed + world.read_resource::<Time>().delta_seconds() * self.speed;
+            if elapsed >= STEP_TIME {
+                self.step_forward(world);
+                // stop at the end, rather than sitting there playing nothing
+                self.autoplay = if self.step < self.replay.actions.len() {
+                    Some(elapsed - STEP_TIME)
+                } else {
+                    None
+                };
+            } else {
+                self.autoplay = Some(elapsed);
+            }
+        }
+        Trans::None
+    }
+}
+
+impl ReplayState {
+    pub fn new(replay: Replay) -> Self {
+        ReplayState {
+            replay,
+            step: 0,
+            autoplay: None,
+            speed: 1.0,
+            view: None,
+            progress: None,
+            description: None,
+            my_ui: vec![],
+        }
+    }
+    pub fn new_boxed(replay: Replay) -> Box<Self> {
+        Box::new(ReplayState::new(replay))
+    }
+
+    fn step_forward(&mut self, world: &mut World) {
+        let outcome = {
+            let mut game = world.write_resource::<Game>();
+            self.replay.play(&mut game, self.step)
+        };
+        if let Some(outcome) = outcome {
+            self.step += 1;
+            self.show(world, Some(outcome));
+        }
+    }
+
+    /// Go back one action, by playing the game again up to the one before it.
+    fn step_back(&mut self, world: &mut World) {
+        if self.step == 0 {
+            return;
+        }
+        self.step -= 1;
+        let mut game = self.replay.game_at(self.step.saturating_sub(1));
+        let outcome = match self.step {
+            0 => None,
+            step => self.replay.play(&mut game, step - 1),
+        };
+        world.insert(game);
+        self.show(world, outcome);
+    }
+
+    /// Bring the board and the text up to date, describing the action that was just played.
+    fn show(&mut self, world: &mut World, outcome: Option<Result<ActionOutcome, AttackError>>) {
+        let game = (*world.read_resource::<Game>()).clone();
+        if let Some(view) = &mut self.view {
+            view.sync(world, game.board());
+        }
+
+        let progress = format!(
+            "step {} of {}, round {}",
+            self.step,
+            self.replay.actions.len(),
+            game.turn()
+        );
+        self.set_text(world, self.progress, &progress);
+
+        let players = world.read_resource::<Players>().clone();
+        let mut description = match outcome {
+            None => "the game is about to start".to_string(),
+            Some(Ok(ActionOutcome::Attack(outcome))) => format!(
+                "{} attacked {}, rolling {} against {}: {}",
+                players.name(outcome.attacker),
+                players.name(outcome.defender),
+                outcome.attacker_total(),
+                outcome.defender_total(),
+                if outcome.captured() {
+                    "captured!"
+                } else {
+                    "repelled."
+                }
+            ),
+            Some(Ok(ActionOutcome::EndTurn(reinforcement))) => format!(
+                "{} ended their turn and received {} dice ({} in reserve)",
+                players.name(reinforcement.player),
+                reinforcement.placed.len(),
+                reinforcement.reserve
+            ),
+            // only a replay from a different version of the rules would get here
+            Some(Err(error)) => format!("this replay doesn't match the rules: {}", error),
+        };
+        if self.step == self.replay.actions.len() {
+            if let Some(winners) = players.winners(&game.remaining()) {
+                let names: Vec<String> = winners.iter().map(|&p| players.name(p)).collect();
+                description.push_str(&format!(" {} won.", names.join(" and ")));
+            }
+        }
+        self.set_text(world, self.description, &description);
+    }
+
+    fn set_text(&self, world: &mut World, entity: Option<Entity>, text: &str) {
+        if let Some(entity) = entity {
+            let mut texts = world.write_storage::<UiText>();
+            texts.get_mut(entity).expect(EXPECTED).text = text.to_string();
+        }
+    }
+}
+
+/// Watch a replay from the start.
+pub fn watch(replay: Replay) -> SimpleTrans {
+    Trans::Switch(ReplayState::new_boxed(replay))
+}
+
+fn create_text(world: &mut World, font: &Handle<FontAsset>, id: &str, y: f32, size: f32) -> Entity {
+    let transform = UiTransform::new(
+        id.to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.0,
+        y,
+        1.0,
+        800.0,
+        40.0,
+    );
+
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.1, 0.1, 0.1, 1.0],
+            size,
+        ))
+        .build()
+}
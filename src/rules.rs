@@ -0,0 +1,406 @@
+//! The rules of the game, applied directly to a `Board`.
+//!
+//! Nothing in here knows about rendering or timing: every action resolves immediately and
+//! reports what happened, so the UI can animate it and the AI can reason about it.
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, PlayerId, TerritoryId};
+
+use std::fmt;
+
+/// The most dice a single territory can hold.
+pub const MAX_DICE: u32 = 8;
+
+/// Dice are ordinary six sided dice; their faces match the sprites in `diceRed.ron`.
+pub const DIE_FACES: u8 = 6;
+
+/// One territory attacking a neighboring one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Attack {
+    pub from: TerritoryId,
+    pub to: TerritoryId,
+}
+
+/// Why an attack is not allowed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttackError {
+    /// the attacking player does not own the territory attacked from
+    NotYourTerritory(TerritoryId),
+    /// a territory needs more than one die to attack
+    NotEnoughDice(TerritoryId),
+    /// players can't attack themselves
+    OwnTerritory(TerritoryId),
+    /// only neighboring territories can be attacked
+    NotAdjacent(TerritoryId, TerritoryId),
+}
+
+impl fmt::Display for AttackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttackError::NotYourTerritory(_) => write!(f, "that territory is not yours"),
+            AttackError::NotEnoughDice(_) => write!(f, "attacking needs more than one die"),
+            AttackError::OwnTerritory(_) => write!(f, "you can't attack your own territory"),
+            AttackError::NotAdjacent(_, _) => write!(f, "you can only attack a neighbor"),
+        }
+    }
+}
+
+/// Whether the defender's territory fell.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttackResult {
+    /// the attacker won; the territory changed hands and took all but one of the attacking dice
+    Captured,
+    /// the defender won (ties go to the defender); the attacker is left with one die
+    Repelled,
+}
+
+/// Everything that happened in a single attack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttackOutcome {
+    pub attack: Attack,
+    pub attacker: PlayerId,
+    pub defender: PlayerId,
+    pub attacker_rolls: Vec<u8>,
+    pub defender_rolls: Vec<u8>,
+    pub result: AttackResult,
+}
+
+impl AttackOutcome {
+    pub fn attacker_total(&self) -> u32 {
+        self.attacker_rolls.iter().map(|&face| face as u32).sum()
+    }
+
+    pub fn defender_total(&self) -> u32 {
+        self.defender_rolls.iter().map(|&face| face as u32).sum()
+    }
+
+    pub fn captured(&self) -> bool {
+        self.result == AttackResult::Captured
+    }
+}
+
+/// Check that `player` may make `attack`, without changing anything.
+pub fn check_attack(board: &Board, player: PlayerId, attack: Attack) -> Result<(), AttackError> {
+    let Attack { from, to } = attack;
+    if board.owner(from) != player {
+        return Err(AttackError::NotYourTerritory(from));
+    }
+    if board.dice(from) < 2 {
+        return Err(AttackError::NotEnoughDice(from));
+    }
+    if board.owner(to) == player {
+        return Err(AttackError::OwnTerritory(to));
+    }
+    if !board.are_adjacent(from, to) {
+        return Err(AttackError::NotAdjacent(from, to));
+    }
+    Ok(())
+}
+
+/// Every attack `player` could make right now.
+pub fn legal_attacks(board: &Board, player: PlayerId) -> Vec<Attack> {
+    board
+        .territories_of(player)
+        .filter(|territory| territory.dice > 1)
+        .flat_map(|territory| {
+            territory
+                .neighbors()
+                .iter()
+                .filter(|&&neighbor| board.owner(neighbor) != player)
+                .map(move |&neighbor| Attack {
+                    from: territory.id,
+                    to: neighbor,
+                })
+        })
+        .collect()
+}
+
+/// Roll both sides' dice and apply the result to the board.
+pub fn attack<R: Rng>(
+    board: &mut Board,
+    player: PlayerId,
+    attack: Attack,
+    rng: &mut R,
+) -> Result<AttackOutcome, AttackError> {
+    check_attack(board, player, attack)?;
+    let Attack { from, to } = attack;
+
+    let defender = board.owner(to);
+    let attacker_rolls = roll(board.dice(from), rng);
+    let defender_rolls = roll(board.dice(to), rng);
+
+    let attacker_total: u32 = attacker_rolls.iter().map(|&face| face as u32).sum();
+    let defender_total: u32 = defender_rolls.iter().map(|&face| face as u32).sum();
+    let result = if attacker_total > defender_total {
+        AttackResult::Captured
+    } else {
+        AttackResult::Repelled
+    };
+    resolve(board, player, attack, result);
+
+    Ok(AttackOutcome {
+        attack,
+        attacker: player,
+        defender,
+        attacker_rolls,
+        defender_rolls,
+        result,
+    })
+}
+
+/// Move the dice and ownership for an attack that ended with `result`, without rolling anything.
+pub fn resolve(board: &mut Board, player: PlayerId, attack: Attack, result: AttackResult) {
+    let Attack { from, to } = attack;
+    if result == AttackResult::Captured {
+        board.set_owner(to, player);
+        board.set_dice(to, board.dice(from) - 1);
+    }
+    board.set_dice(from, 1);
+}
+
+/// Roll `count` dice.
+pub fn roll<R: Rng>(count: u32, rng: &mut R) -> Vec<u8> {
+    (0..count).map(|_| rng.gen_range(1, DIE_FACES + 1)).collect()
+}
+
+/// The dice a player received at the end of their turn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reinforcement {
+    pub player: PlayerId,
+    /// dice earned this turn: one per territory in the player's largest connected group
+    pub earned: u32,
+    /// where each die went, in the order they were placed
+    pub placed: Vec<TerritoryId>,
+    /// dice that did not fit anywhere, kept for the next turn
+    pub reserve: u32,
+}
+
+/// The number of territories in `player`'s largest group of connected territories.
+pub fn largest_group(board: &Board, player: PlayerId) -> usize {
+    board
+        .connected_groups(|territory| territory.owner == player)
+        .first()
+        .map_or(0, |group| group.len())
+}
+
+/// End of turn reinforcement: `player` earns a die for every territory in their largest connected
+/// group, and those dice plus their `reserve` are dropped one at a time onto random territories
+/// that still have room.
+pub fn reinforce<R: Rng>(
+    board: &mut Board,
+    player: PlayerId,
+    reserve: u32,
+    rng: &mut R,
+) -> Reinforcement {
+    let earned = largest_group(board, player) as u32;
+    let (placed, reserve) = place_dice(board, player, earned + reserve, rng);
+    Reinforcement {
+        player,
+        earned,
+        placed,
+        reserve,
+    }
+}
+
+/// Drop `count` dice onto random territories owned by `player`, never going over `MAX_DICE`.
+/// Returns where each die went and how many were left over.
+pub fn place_dice<R: Rng>(
+    board: &mut Board,
+    player: PlayerId,
+    count: u32,
+    rng: &mut R,
+) -> (Vec<TerritoryId>, u32) {
+    let mut remaining = count;
+    let mut placed = vec![];
+    while remaining > 0 {
+        let open: Vec<TerritoryId> = board
+            .territories_of(player)
+            .filter(|territory| territory.dice < MAX_DICE)
+            .map(|territory| territory.id)
+            .collect();
+        let territory = match open.choose(rng) {
+            Some(&territory) => territory,
+            None => break,
+        };
+        board.set_dice(territory, board.dice(territory) + 1);
+        placed.push(territory);
+        remaining -= 1;
+    }
+    (placed, remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Offset;
+    use crate::seed::GameRng;
+
+    use rand::SeedableRng;
+
+    const RED: PlayerId = PlayerId(0);
+    const BLUE: PlayerId = PlayerId(1);
+
+    /// Four one-hex territories in a row, each bordering the next: red with 3 dice, blue with 2,
+    /// red with 1, and blue with 4.
+    fn row() -> Board {
+        let mut board = Board::new(4, 1);
+        let territories = [(RED, 3), (BLUE, 2), (RED, 1), (BLUE, 4)];
+        for (col, &(owner, dice)) in territories.iter().enumerate() {
+            let territory = board.add_territory(owner, dice);
+            board.assign(Offset::new(col as i32, 0).into(), territory);
+        }
+        board.rebuild_adjacency();
+        board
+    }
+
+    fn attack_on(from: usize, to: usize) -> Attack {
+        Attack {
+            from: TerritoryId(from),
+            to: TerritoryId(to),
+        }
+    }
+
+    #[test]
+    fn the_row_borders_only_its_neighbors() {
+        let board = row();
+        for a in 0..4 {
+            for b in 0..4 {
+                let adjacent = (a as i32 - b as i32).abs() == 1;
+                assert_eq!(board.are_adjacent(TerritoryId(a), TerritoryId(b)), adjacent);
+            }
+        }
+    }
+
+    #[test]
+    fn check_attack_explains_every_refusal() {
+        let board = row();
+        assert_eq!(check_attack(&board, RED, attack_on(0, 1)), Ok(()));
+        assert_eq!(
+            check_attack(&board, RED, attack_on(1, 0)),
+            Err(AttackError::NotYourTerritory(TerritoryId(1)))
+        );
+        assert_eq!(
+            check_attack(&board, RED, attack_on(2, 1)),
+            Err(AttackError::NotEnoughDice(TerritoryId(2)))
+        );
+        assert_eq!(
+            check_attack(&board, RED, attack_on(0, 2)),
+            Err(AttackError::OwnTerritory(TerritoryId(2)))
+        );
+        assert_eq!(
+            check_attack(&board, RED, attack_on(0, 3)),
+            Err(AttackError::NotAdjacent(TerritoryId(0), TerritoryId(3)))
+        );
+    }
+
+    #[test]
+    fn refused_attacks_change_nothing() {
+        let mut board = row();
+        let mut rng = GameRng::seed_from_u64(7);
+        assert!(attack(&mut board, RED, attack_on(0, 3), &mut rng).is_err());
+        assert_eq!(board, row());
+    }
+
+    #[test]
+    fn legal_attacks_come_from_territories_with_dice_to_spare() {
+        let board = row();
+        assert_eq!(legal_attacks(&board, RED), vec![attack_on(0, 1)]);
+
+        let mut blue = legal_attacks(&board, BLUE);
+        blue.sort_by_key(|attack| (attack.from.0, attack.to.0));
+        let expected = vec![attack_on(1, 0), attack_on(1, 2), attack_on(3, 2)];
+        assert_eq!(blue, expected);
+    }
+
+    #[test]
+    fn attacks_resolve_as_the_dice_fell() {
+        let mut rng = GameRng::seed_from_u64(0xd1ce);
+        let (mut captured, mut repelled) = (0, 0);
+        for _ in 0..50 {
+            let mut board = row();
+            let outcome = attack(&mut board, RED, attack_on(0, 1), &mut rng).unwrap();
+            assert_eq!(outcome.attacker_rolls.len(), 3);
+            assert_eq!(outcome.defender_rolls.len(), 2);
+            assert_eq!(outcome.defender, BLUE);
+            assert_eq!(
+                outcome.captured(),
+                outcome.attacker_total() > outcome.defender_total()
+            );
+
+            // the attacker keeps one die either way
+            assert_eq!(board.owner(TerritoryId(0)), RED);
+            assert_eq!(board.dice(TerritoryId(0)), 1);
+            if outcome.captured() {
+                // and moves the rest in
+                assert_eq!(board.owner(TerritoryId(1)), RED);
+                assert_eq!(board.dice(TerritoryId(1)), 2);
+                captured += 1;
+            } else {
+                assert_eq!(board.owner(TerritoryId(1)), BLUE);
+                assert_eq!(board.dice(TerritoryId(1)), 2);
+                repelled += 1;
+            }
+        }
+        assert!(captured > 0 && repelled > 0);
+    }
+
+    fn total_dice(board: &Board, player: PlayerId) -> u32 {
+        board
+            .territories_of(player)
+            .map(|territory| territory.dice)
+            .sum()
+    }
+
+    #[test]
+    fn placed_dice_stop_at_the_limit() {
+        let mut board = row();
+        let mut rng = GameRng::seed_from_u64(3);
+        // red's two territories hold 4 dice, and room for 12 more
+        let (placed, left) = place_dice(&mut board, RED, 20, &mut rng);
+        assert_eq!(placed.len(), 12);
+        assert_eq!(left, 8);
+        for territory in board.territories_of(RED) {
+            assert_eq!(territory.dice, MAX_DICE);
+        }
+        assert_eq!(total_dice(&board, BLUE), 6);
+    }
+
+    #[test]
+    fn reinforcements_carry_over_what_does_not_fit() {
+        let mut board = row();
+        let mut rng = GameRng::seed_from_u64(11);
+        // red's territories don't touch, so red earns one die
+        let reinforcement = reinforce(&mut board, RED, 3, &mut rng);
+        assert_eq!(reinforcement.earned, 1);
+        assert_eq!(reinforcement.placed.len(), 4);
+        assert_eq!(reinforcement.reserve, 0);
+        assert_eq!(total_dice(&board, RED), 8);
+
+        board.set_dice(TerritoryId(0), MAX_DICE);
+        board.set_dice(TerritoryId(2), MAX_DICE);
+        let reinforcement = reinforce(&mut board, RED, 2, &mut rng);
+        assert!(reinforcement.placed.is_empty());
+        assert_eq!(reinforcement.reserve, 3);
+
+        // and the reserve goes down again once there is room
+        board.set_dice(TerritoryId(0), MAX_DICE - 2);
+        let reinforcement = reinforce(&mut board, RED, reinforcement.reserve, &mut rng);
+        assert_eq!(reinforcement.placed, vec![TerritoryId(0); 2]);
+        assert_eq!(reinforcement.reserve, 2);
+    }
+
+    #[test]
+    fn the_same_seed_places_the_same_dice() {
+        let place = |seed: u64| {
+            let mut board = row();
+            let mut rng = GameRng::seed_from_u64(seed);
+            let reinforcement = reinforce(&mut board, BLUE, 5, &mut rng);
+            (board, reinforcement)
+        };
+        for seed in 0..20 {
+            assert_eq!(place(seed), place(seed));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Saved games, kept as RON files in the `saves` folder.
+//!
+//! A save holds everything needed to carry on exactly where a game was left, dice rolls and all:
+//! the game itself, who is playing, and the seed, settings and seats it was started with, for
+//! rematches and replays.
+//! Each file is stamped with a version, and files from other versions are refused rather than
+//! guessed at.
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameSettings;
+use crate::game::Game;
+use crate::players::Players;
+use crate::seed::MapSeed;
+use crate::setup::GameSetup;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The version of the save format this build writes and reads. Bump it whenever anything saved
+/// changes shape, except for new fields marked `#[serde(default)]`, which older saves load
+/// without.
+pub const VERSION: u32 = 2;
+
+/// The save written when the game is closed, and picked up again by "continue".
+pub const AUTOSAVE: &str = "autosave.ron";
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SavedGame {
+    pub version: u32,
+    pub seed: MapSeed,
+    /// the settings the map was generated from, with the player count filled in
+    pub settings: GameSettings,
+    pub setup: GameSetup,
+    pub players: Players,
+    pub game: Game,
+}
+
+/// Just the version, for telling why a save didn't load.
+#[derive(Deserialize)]
+#[serde(rename = "SavedGame")]
+struct Header {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// the file isn't a save, or is damaged
+    Format(String),
+    /// the file was saved by a different version of the game
+    Version(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{}", error),
+            SaveError::Format(error) => write!(f, "not a saved game: {}", error),
+            SaveError::Version(version) => write!(
+                f,
+                "saved by another version of the game (save version {}, expected {})",
+                version, VERSION
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> SaveError {
+        SaveError::Io(error)
+    }
+}
+
+impl SavedGame {
+    pub fn new(
+        seed: MapSeed,
+        settings: GameSettings,
+        setup: GameSetup,
+        players: Players,
+        game: Game,
+    ) -> SavedGame {
+        SavedGame {
+            version: VERSION,
+            seed,
+            settings,
+            setup,
+            players,
+            game,
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|error| SaveError::Format(error.to_string()))
+    }
+
+    pub fn from_ron(text: &str) -> Result<SavedGame, SaveError> {
+        match ron::de::from_str::<SavedGame>(text) {
+            Ok(saved) if saved.version == VERSION => Ok(saved),
+            Ok(saved) => Err(SaveError::Version(saved.version)),
+            Err(error) => {
+                // a save from another version may not fit at all, so see if that is why. RON can
+                // only skip over numbers that fit in an i64, so this can't go first.
+                match ron::de::from_str::<Header>(text) {
+                    Ok(header) if header.version != VERSION => {
+                        Err(SaveError::Version(header.version))
+                    }
+                    _ => Err(SaveError::Format(error.to_string())),
+                }
+            }
+        }
+    }
+
+    /// Write the save to `path`, making its folder if need be.
+    pub fn save(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<SavedGame, SaveError> {
+        SavedGame::from_ron(&fs::read_to_string(path)?)
+    }
+}
+
+/// The folder saves are kept in, under the game's root folder.
+pub fn save_dir(root: &Path) -> PathBuf {
+    root.join("saves")
+}
+
+/// Every save in `dir`, newest first.
+pub fn list_saves(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut saves: Vec<(PathBuf, Option<std::time::SystemTime>)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "ron"))
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect();
+    saves.sort_by(|a, b| b.1.cmp(&a.1));
+    saves.into_iter().map(|(path, _)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PlayerId;
+    use crate::mapgen;
+    use crate::players::{Controller, PlayerDescriptor};
+    use crate::rules;
+    use crate::setup::Seat;
+
+    fn saved_game() -> SavedGame {
+        let seed = MapSeed(0x5a7e);
+        let settings = GameSettings {
+            width: 16,
+            height: 16,
+            area_size: 6,
+            player_count: Some(3),
+            ..GameSettings::default()
+        };
+        let board = mapgen::generate(&settings, &mut seed.rng()).board;
+        let setup = GameSetup {
+            seats: vec![Seat::Human, Seat::Human, Seat::Human],
+            first: 0,
+            max_seats: 8,
+            names: vec![],
+            colors: vec![],
+        };
+        let players = Players(
+            (0..3)
+                .map(|id| PlayerDescriptor {
+                    id: PlayerId(id),
+                    name: format!("player {}", id + 1),
+                    color: (0.1 * id as f32, 0.5, 0.3),
+                    controller: Controller::Human,
+                    team: None,
+                })
+                .collect(),
+        );
+        let mut game = Game::new(board, setup.turn_order(), seed.game_rng());
+        // play a little, so there are rolls, captures and reserves to get right
+        for _ in 0..6 {
+            let player = game.current_player();
+            if let Some(&attack) = rules::legal_attacks(game.board(), player).first() {
+                game.attack(attack).unwrap();
+            }
+            game.end_turn();
+        }
+        SavedGame::new(seed, settings, setup, players, game)
+    }
+
+    #[test]
+    fn reloads_the_same_game() {
+        let saved = saved_game();
+        let loaded = SavedGame::from_ron(&saved.to_ron().unwrap()).unwrap();
+        assert_eq!(saved, loaded);
+        assert_eq!(saved.to_ron().unwrap(), loaded.to_ron().unwrap());
+
+        // and both play on exactly the same way
+        let (mut a, mut b) = (saved.game, loaded.game);
+        for _ in 0..10 {
+            let player = a.current_player();
+            for attack in rules::legal_attacks(a.board(), player).into_iter().take(2) {
+                assert_eq!(a.attack(attack), b.attack(attack));
+            }
+            assert_eq!(a.end_turn(), b.end_turn());
+        }
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn refuses_other_versions() {
+        let mut saved = saved_game();
+        saved.version = VERSION + 1;
+        match SavedGame::from_ron(&saved.to_ron().unwrap()) {
+            Err(SaveError::Version(version)) => assert_eq!(version, VERSION + 1),
+            other => panic!("expected a version error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn refuses_garbage() {
+        match SavedGame::from_ron("Palette(players: [])") {
+            Err(SaveError::Format(_)) => {}
+            other => panic!("expected a format error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
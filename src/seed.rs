@@ -0,0 +1,131 @@
+//! Seeds for reproducible games. The same seed and settings always give the same map.
+
+use rand::{thread_rng, Error, Rng, RngCore, SeedableRng};
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The random number generator behind everything in a game that has to be reproducible.
+/// Anything only for show (like animations) should use `thread_rng` instead.
+///
+/// It is a PCG generator that also keeps its seed and how many numbers it has handed out, which
+/// is how it is saved: loading seeds a fresh generator and skips ahead to the same place. (The
+/// generator's own state is a pair of 128-bit numbers, which RON can't store.)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "RngPosition", into = "RngPosition")]
+pub struct GameRng {
+    pcg: Pcg64,
+    seed: u64,
+    steps: u64,
+}
+
+/// What is saved of a `GameRng`.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+struct RngPosition {
+    seed: u64,
+    steps: u64,
+}
+
+impl From<RngPosition> for GameRng {
+    fn from(position: RngPosition) -> GameRng {
+        let mut rng = GameRng::seed_from_u64(position.seed);
+        for _ in 0..position.steps {
+            rng.next_u64();
+        }
+        rng
+    }
+}
+
+impl From<GameRng> for RngPosition {
+    fn from(rng: GameRng) -> RngPosition {
+        RngPosition {
+            seed: rng.seed,
+            steps: rng.steps,
+        }
+    }
+}
+
+/// Two generators are the same if they will give the same numbers from here on.
+impl PartialEq for GameRng {
+    fn eq(&self, other: &GameRng) -> bool {
+        self.seed == other.seed && self.steps == other.steps
+    }
+}
+
+impl Eq for GameRng {}
+
+impl SeedableRng for GameRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> GameRng {
+        GameRng::seed_from_u64(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> GameRng {
+        GameRng {
+            pcg: Pcg64::seed_from_u64(seed),
+            seed,
+            steps: 0,
+        }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.steps += 1;
+        self.pcg.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.steps += 1;
+        self.pcg.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // eight bytes to a step, with any left over taking one more
+        self.steps += (dest.len() as u64 + 7) / 8;
+        self.pcg.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A map seed. It is shown to players as hex digits so it can be shared and typed back in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MapSeed(pub u64);
+
+impl MapSeed {
+    /// A fresh seed, kept to eight digits so it is easy to pass around.
+    pub fn random() -> MapSeed {
+        MapSeed(thread_rng().gen::<u32>() as u64)
+    }
+
+    /// The generator used to build the map.
+    pub fn rng(self) -> GameRng {
+        GameRng::seed_from_u64(self.0)
+    }
+
+    /// The generator for dice rolls once play starts, kept apart from map generation so that
+    /// changes to the generator don't change how games on a given map play out.
+    pub fn game_rng(self) -> GameRng {
+        GameRng::seed_from_u64(self.0 ^ 0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+impl fmt::Display for MapSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X}", self.0)
+    }
+}
+
+impl FromStr for MapSeed {
+    type Err = std::num::ParseIntError;
+    fn from_str(s: &str) -> Result<MapSeed, Self::Err> {
+        u64::from_str_radix(s.trim(), 16).map(MapSeed)
+    }
+}
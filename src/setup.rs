@@ -0,0 +1,231 @@
+//! Who is playing: the seats around the table, who sits in them, and who goes first.
+
+use crate::board::PlayerId;
+use crate::config::GameSettings;
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+pub const MIN_PLAYERS: usize = 2;
+/// How many seats a new game has when the settings don't say.
+pub const DEFAULT_PLAYERS: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+/// Who sits in a seat.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Seat {
+    Human,
+    Ai(Difficulty),
+    /// nobody; the seat is left out of the game
+    Empty,
+}
+
+impl Seat {
+    /// The next choice for a seat, for cycling through them with a single button.
+    pub fn next(self) -> Seat {
+        match self {
+            Seat::Human => Seat::Ai(Difficulty::Easy),
+            Seat::Ai(Difficulty::Easy) => Seat::Ai(Difficulty::Normal),
+            Seat::Ai(Difficulty::Normal) => Seat::Ai(Difficulty::Hard),
+            Seat::Ai(Difficulty::Hard) => Seat::Empty,
+            Seat::Empty => Seat::Human,
+        }
+    }
+}
+
+impl fmt::Display for Seat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Seat::Human => write!(f, "human"),
+            Seat::Ai(Difficulty::Easy) => write!(f, "AI (easy)"),
+            Seat::Ai(Difficulty::Normal) => write!(f, "AI (normal)"),
+            Seat::Ai(Difficulty::Hard) => write!(f, "AI (hard)"),
+            Seat::Empty => write!(f, "empty"),
+        }
+    }
+}
+
+/// The choices made on the new game screen.
+///
+/// Empty seats are dropped when the game starts, so the players are the occupied seats in order,
+/// and player ids count occupied seats only.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GameSetup {
+    pub seats: Vec<Seat>,
+    /// index into `seats` of the seat that plays first
+    pub first: usize,
+    /// how many seats there can be, which is how many players the palette has colors for
+    pub max_seats: usize,
+    /// names typed in for each seat; a seat without one goes by the name of its color
+    #[serde(default)]
+    pub names: Vec<Option<String>>,
+    /// the palette entry each seat takes its color from. No two seats share one.
+    #[serde(default)]
+    pub colors: Vec<usize>,
+}
+
+impl GameSetup {
+    /// A human in the first seat against AIs, as many as the settings ask for.
+    pub fn from_settings(settings: &GameSettings, max_seats: usize) -> GameSetup {
+        let mut setup = GameSetup {
+            seats: vec![Seat::Human],
+            first: 0,
+            max_seats: max_seats.max(MIN_PLAYERS),
+            names: vec![],
+            colors: vec![],
+        };
+        setup.set_seat_count(settings.player_count.map_or(DEFAULT_PLAYERS, |count| count as usize));
+        setup
+    }
+
+    /// Add or remove seats at the end of the table, keeping it between `MIN_PLAYERS` and
+    /// `max_seats` seats.
+    pub fn set_seat_count(&mut self, count: usize) {
+        let count = count.max(MIN_PLAYERS).min(self.max_seats);
+        self.seats.resize(count, Seat::Ai(Difficulty::Normal));
+        self.names.truncate(count);
+        self.colors.truncate(count);
+        self.fill_colors();
+        if self.first >= count {
+            self.first = 0;
+        }
+    }
+
+    /// The name typed in for `seat`, if there is one.
+    pub fn name(&self, seat: usize) -> Option<&str> {
+        self.names.get(seat).and_then(|name| name.as_ref().map(String::as_str))
+    }
+
+    /// Name the player in `seat`. A blank name goes back to the name of the seat's color.
+    pub fn set_name(&mut self, seat: usize, name: &str) {
+        if self.names.len() <= seat {
+            self.names.resize(seat + 1, None);
+        }
+        let name = name.trim();
+        self.names[seat] = if name.is_empty() {
+            None
+        } else {
+            Some(name.to_string())
+        };
+    }
+
+    /// The palette entry `seat` takes its color from.
+    pub fn color(&self, seat: usize) -> usize {
+        self.colors.get(seat).cloned().unwrap_or(seat)
+    }
+
+    /// Give `seat` the next color in the palette, trading colors with whoever had it.
+    pub fn next_color(&mut self, seat: usize) {
+        self.fill_colors();
+        let old = self.colors[seat];
+        let new = (old + 1) % self.max_seats;
+        if let Some(other) = self.colors.iter().position(|&color| color == new) {
+            self.colors[other] = old;
+        }
+        self.colors[seat] = new;
+    }
+
+    /// Give every seat without a color the first one nobody has.
+    fn fill_colors(&mut self) {
+        while self.colors.len() < self.seats.len() {
+            let free = (0..self.max_seats)
+                .find(|color| !self.colors.contains(color))
+                .unwrap_or(0);
+            self.colors.push(free);
+        }
+    }
+
+    /// The number of seats that are not empty.
+    pub fn player_count(&self) -> usize {
+        self.occupied().count()
+    }
+
+    /// A game needs at least two players.
+    pub fn is_playable(&self) -> bool {
+        self.player_count() >= MIN_PLAYERS && self.seats[self.first] != Seat::Empty
+    }
+
+    /// Pass the first turn to the next occupied seat.
+    pub fn next_first(&mut self) {
+        for _ in 0..self.seats.len() {
+            self.first = (self.first + 1) % self.seats.len();
+            if self.seats[self.first] != Seat::Empty {
+                break;
+            }
+        }
+    }
+
+    /// The players in the order they take their turns, starting with the first seat.
+    pub fn turn_order(&self) -> Vec<PlayerId> {
+        let first = self.occupied().take_while(|&(i, _)| i < self.first).count();
+        let count = self.player_count();
+        (0..count).map(|i| PlayerId((first + i) % count)).collect()
+    }
+
+    fn occupied(&self) -> impl Iterator<Item = (usize, &Seat)> {
+        self.seats
+            .iter()
+            .enumerate()
+            .filter(|(_, &seat)| seat != Seat::Empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(seats: usize) -> GameSetup {
+        let settings = GameSettings {
+            player_count: Some(seats as u32),
+            ..GameSettings::default()
+        };
+        GameSetup::from_settings(&settings, 6)
+    }
+
+    fn assert_colors_unique(setup: &GameSetup) {
+        let mut colors: Vec<usize> = (0..setup.seats.len()).map(|s| setup.color(s)).collect();
+        colors.sort();
+        colors.dedup();
+        assert_eq!(colors.len(), setup.seats.len(), "colors {:?}", setup.colors);
+    }
+
+    #[test]
+    fn seats_never_share_a_color() {
+        let mut setup = setup(4);
+        assert_eq!(setup.colors, vec![0, 1, 2, 3]);
+
+        // taking a color that is in use swaps it
+        setup.next_color(0);
+        assert_eq!(setup.colors, vec![1, 0, 2, 3]);
+        setup.next_color(3);
+        setup.next_color(3);
+        assert_eq!(setup.color(3), 5);
+        assert_colors_unique(&setup);
+
+        // seats added later get whatever is free
+        setup.set_seat_count(2);
+        setup.set_seat_count(6);
+        assert_colors_unique(&setup);
+        for _ in 0..7 {
+            setup.next_color(4);
+            assert_colors_unique(&setup);
+        }
+    }
+
+    #[test]
+    fn blank_names_fall_back() {
+        let mut setup = setup(3);
+        assert_eq!(setup.name(2), None);
+        setup.set_name(2, "  Ada ");
+        assert_eq!(setup.name(2), Some("Ada"));
+        setup.set_name(2, "   ");
+        assert_eq!(setup.name(2), None);
+    }
+}